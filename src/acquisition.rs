@@ -0,0 +1,538 @@
+//! Acquisition processing: how the signal between two points of a trace becomes the trace.
+
+use std::collections::VecDeque;
+
+use crate::measure::format_si;
+use crate::signal::{Sample, SignalSource};
+
+/// Signal evaluations per trace point in [`AcquisitionMode::PeakDetect`] and
+/// [`AcquisitionMode::HighRes`], as far as [`MAX_OVERSAMPLED_EVALUATIONS`] allows.
+pub const OVERSAMPLING: usize = 16;
+
+/// Signal evaluations an oversampled record may take.
+pub const MAX_OVERSAMPLED_EVALUATIONS: usize = 20_000_000;
+
+/// Record lengths (samples) on offer.
+pub const MEMORY_DEPTHS: [usize; 13] = [
+    1_000, 2_000, 5_000, 10_000, 20_000, 50_000, 100_000, 200_000, 500_000, 1_000_000, 2_000_000,
+    5_000_000, 10_000_000,
+];
+
+/// Sample rates (S/s) on offer.
+pub const SAMPLE_RATES: [f64; 19] = [
+    1e3, 2e3, 5e3, 10e3, 20e3, 50e3, 100e3, 200e3, 500e3, 1e6, 2e6, 5e6, 10e6, 20e6, 50e6, 100e6,
+    200e6, 500e6, 1e9,
+];
+
+/// Samples an [`Averager`] may keep, over all the records it averages.
+pub const MAX_AVERAGED_SAMPLES: usize = 20_000_000;
+
+/// ADC resolutions (bits) on offer.
+pub const RESOLUTIONS: [u32; 4] = [8, 10, 12, 16];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum AcquisitionMode {
+    /// One sample per point.
+    Sample,
+
+    /// The minimum and maximum of each point's interval, so narrow spikes show.
+    PeakDetect,
+
+    /// Point-by-point mean of the last N acquisitions.
+    Average,
+
+    /// Mean of each point's interval (box-car filter), for less noise and more resolution.
+    HighRes,
+}
+
+impl AcquisitionMode {
+    pub const ALL: [Self; 4] = [Self::Sample, Self::PeakDetect, Self::Average, Self::HighRes];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sample => "Sample",
+            Self::PeakDetect => "Peak detect",
+            Self::Average => "Average",
+            Self::HighRes => "High-res",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Acquisition {
+    pub mode: AcquisitionMode,
+
+    /// Number of acquisitions averaged in [`AcquisitionMode::Average`], as far as
+    /// [`MAX_AVERAGED_SAMPLES`] allows.
+    pub averages: usize,
+
+    /// Samples per record.
+    pub memory_depth: usize,
+
+    /// S/s, unless `auto_rate` is set.
+    pub sample_rate: f64,
+
+    /// Choose the sample rate that spreads the record over the screen's width of time.
+    pub auto_rate: bool,
+
+    /// Bits of the ADC, one of [`RESOLUTIONS`]. `None` keeps the exact signal values.
+    pub resolution: Option<u32>,
+}
+
+impl Default for Acquisition {
+    fn default() -> Self {
+        Self {
+            mode: AcquisitionMode::Sample,
+            averages: 16,
+            memory_depth: 10_000,
+            sample_rate: 1e6,
+            auto_rate: true,
+            resolution: None,
+        }
+    }
+}
+
+impl Acquisition {
+    /// Short description for the status line, e.g.
+    /// `Average (16), 10.00 kS at 1.000 MS/s, 8 bit`.
+    pub fn status(&self, screen_time: f64) -> String {
+        let mode = match self.mode {
+            AcquisitionMode::Average => {
+                format!("{} ({})", self.mode.name(), self.average_count())
+            }
+            mode => mode.name().to_owned(),
+        };
+        let mut status = format!(
+            "{mode}, {} at {}",
+            format_si(self.memory_depth as f64, "S"),
+            format_si(self.rate(screen_time), "S/s")
+        );
+        if let Some(bits) = self.resolution {
+            status.push_str(&format!(", {bits} bit"));
+        }
+        status
+    }
+
+    /// Number of acquisitions averaged: `averages`, or fewer if that many records would not
+    /// fit in [`MAX_AVERAGED_SAMPLES`].
+    pub fn average_count(&self) -> usize {
+        self.averages
+            .min(MAX_AVERAGED_SAMPLES / self.memory_depth.max(1))
+            .max(1)
+    }
+
+    /// Trace samples per record point: two in peak detect, one otherwise.
+    pub fn samples_per_point(&self) -> usize {
+        if self.mode == AcquisitionMode::PeakDetect {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Signal evaluations for one channel's record.
+    pub fn evaluations(&self) -> usize {
+        match self.mode {
+            AcquisitionMode::PeakDetect | AcquisitionMode::HighRes => {
+                self.memory_depth * oversampling(self.memory_depth)
+            }
+            AcquisitionMode::Sample | AcquisitionMode::Average => self.memory_depth,
+        }
+    }
+
+    /// Sample rate (S/s) when the screen is `screen_time` (s) wide.
+    pub fn rate(&self, screen_time: f64) -> f64 {
+        if self.auto_rate {
+            (self.memory_depth.max(2) - 1) as f64 / screen_time
+        } else {
+            self.sample_rate
+        }
+    }
+
+    /// Start and end (s) of the record, centred on the trigger point.
+    pub fn record(&self, screen_time: f64) -> (f64, f64) {
+        let half = (self.memory_depth.max(2) - 1) as f64 / self.rate(screen_time) / 2.0;
+        (-half, half)
+    }
+
+    /// A trace of `count` points covering `t_start..=t_end`, each standing for the interval
+    /// of signal around it.
+    ///
+    /// Peak detect gives two samples per point, the minimum and the maximum in the order they
+    /// occurred, so the trace keeps an even time step of half the point spacing.
+    ///
+    /// Every evaluation of the signal goes through the `adc`, if any, before it is combined
+    /// into a point, so high-res averaging gains resolution over the ADC's.
+    pub fn sample(
+        &self,
+        signal: &dyn SignalSource,
+        t_start: f64,
+        t_end: f64,
+        count: usize,
+        adc: Option<Adc>,
+    ) -> Vec<Sample> {
+        let samples = |t_start: f64, t_end: f64, count: usize| {
+            let mut samples = signal.samples(t_start, t_end, count);
+            if let Some(adc) = adc {
+                for s in &mut samples {
+                    s.v = adc.convert(s.v);
+                }
+            }
+            samples
+        };
+        if count < 2
+            || !matches!(
+                self.mode,
+                AcquisitionMode::PeakDetect | AcquisitionMode::HighRes
+            )
+        {
+            return samples(t_start, t_end, count);
+        }
+        let dt = (t_end - t_start) / (count - 1) as f64;
+        let oversampling = oversampling(count);
+        let mut trace = Vec::with_capacity(count * 2);
+        for i in 0..count {
+            let t = t_start + i as f64 * dt;
+            let bucket = samples(
+                t - dt / 2.0 + dt / oversampling as f64 / 2.0,
+                t + dt / 2.0 - dt / oversampling as f64 / 2.0,
+                oversampling,
+            );
+            if self.mode == AcquisitionMode::HighRes {
+                let mean = bucket.iter().map(|s| s.v).sum::<f64>() / oversampling as f64;
+                trace.push(Sample { t, v: mean });
+                continue;
+            }
+            let by_voltage = |a: &&Sample, b: &&Sample| a.v.total_cmp(&b.v);
+            let (Some(min), Some(max)) = (
+                bucket.iter().min_by(by_voltage),
+                bucket.iter().max_by(by_voltage),
+            ) else {
+                continue;
+            };
+            let (first, second) = if min.t <= max.t {
+                (min.v, max.v)
+            } else {
+                (max.v, min.v)
+            };
+            trace.push(Sample {
+                t: t - dt / 4.0,
+                v: first,
+            });
+            trace.push(Sample {
+                t: t + dt / 4.0,
+                v: second,
+            });
+        }
+        trace
+    }
+}
+
+/// Signal evaluations per point of an oversampled record of `count` points: [`OVERSAMPLING`],
+/// or fewer for a record too long for [`MAX_OVERSAMPLED_EVALUATIONS`].
+fn oversampling(count: usize) -> usize {
+    OVERSAMPLING
+        .min(MAX_OVERSAMPLED_EVALUATIONS / count.max(1))
+        .max(1)
+}
+
+/// Analog-to-digital converter: clips the input to its range and rounds it to one of
+/// `2^bits` evenly spaced levels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adc {
+    pub bits: u32,
+
+    /// Input range (V). The lowest level is `low`, the highest one step below `high`.
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Adc {
+    /// Voltage step (V) between adjacent levels.
+    pub fn lsb(&self) -> f64 {
+        (self.high - self.low) / f64::from(1u32 << self.bits.min(31))
+    }
+
+    /// The level nearest to `v`, or the rail it is beyond.
+    pub fn convert(&self, v: f64) -> f64 {
+        let lsb = self.lsb();
+        let top = f64::from((1u32 << self.bits.min(31)) - 1);
+        let code = ((v - self.low) / lsb).round().clamp(0.0, top);
+        self.low + code * lsb
+    }
+}
+
+/// Moving average of the last few acquisitions of one channel.
+#[derive(Clone, Debug, Default)]
+pub struct Averager {
+    /// Newest first.
+    traces: VecDeque<Vec<Sample>>,
+}
+
+impl Averager {
+    /// Adds an acquisition and returns the point-by-point mean of the last `count`.
+    ///
+    /// Traces with a different length or time span than the previous one (a new timebase)
+    /// restart the average.
+    pub fn add(&mut self, trace: Vec<Sample>, count: usize) -> Vec<Sample> {
+        if self
+            .traces
+            .front()
+            .is_some_and(|previous| !same_time_base(previous, &trace))
+        {
+            self.traces.clear();
+        }
+        self.traces.push_front(trace);
+        self.traces.truncate(count.max(1));
+
+        let n = self.traces.len() as f64;
+        let mut mean = self.traces[0].clone();
+        for (i, sample) in mean.iter_mut().enumerate() {
+            sample.v = self.traces.iter().map(|trace| trace[i].v).sum::<f64>() / n;
+        }
+        mean
+    }
+
+    pub fn clear(&mut self) {
+        self.traces.clear();
+    }
+}
+
+/// Whether two traces have the same length and record window, to within rounding: the
+/// times are relative to a trigger point far along the acquisition clock, so they differ
+/// in the last few bits from one acquisition to the next.
+fn same_time_base(a: &[Sample], b: &[Sample]) -> bool {
+    let (Some(a_first), Some(a_last), Some(b_first), Some(b_last)) =
+        (a.first(), a.last(), b.first(), b.last())
+    else {
+        return a.len() == b.len();
+    };
+    let tolerance = 1e-6 * (b_last.t - b_first.t).abs().max(f64::MIN_POSITIVE);
+    a.len() == b.len()
+        && (a_first.t - b_first.t).abs() <= tolerance
+        && (a_last.t - b_last.t).abs() <= tolerance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Acquisition, AcquisitionMode, Adc, Averager};
+    use crate::measure::{EdgeThresholds, Measurement, Measurements};
+    use crate::signal::{Generator, Sample, SignalSource, WaveformType};
+
+    /// 0 V with a 1 V spike from 0.52 to 0.53 s.
+    struct Spike;
+
+    impl SignalSource for Spike {
+        fn voltage_at(&self, t: f64) -> f64 {
+            if (0.52..0.53).contains(&t) { 1.0 } else { 0.0 }
+        }
+    }
+
+    /// 1 V/s ramp through 0 V at 0 s.
+    struct Ramp;
+
+    impl SignalSource for Ramp {
+        fn voltage_at(&self, t: f64) -> f64 {
+            t
+        }
+    }
+
+    fn acquisition(mode: AcquisitionMode) -> Acquisition {
+        Acquisition {
+            mode,
+            ..Acquisition::default()
+        }
+    }
+
+    #[test]
+    fn peak_detect_keeps_narrow_spikes() {
+        // Points every 0.1 s: plain sampling steps over the spike
+        let sampled = acquisition(AcquisitionMode::Sample).sample(&Spike, 0.0, 1.0, 11, None);
+        assert!(
+            sampled.iter().all(|s| s.v == 0.0),
+            "sampling misses the spike"
+        );
+
+        let peaks = acquisition(AcquisitionMode::PeakDetect).sample(&Spike, 0.0, 1.0, 11, None);
+        assert_eq!(peaks.len(), 22, "two samples per point");
+        let hits: Vec<f64> = peaks.iter().filter(|s| s.v == 1.0).map(|s| s.t).collect();
+        assert!(
+            hits.len() == 1 && (hits[0] - 0.525).abs() < 1e-12,
+            "only the max of the point at 0.5 s, in its second half: {hits:?}"
+        );
+        let steps: Vec<f64> = peaks.windows(2).map(|p| p[1].t - p[0].t).collect();
+        assert!(
+            steps.iter().all(|step| (step - 0.05).abs() < 1e-12),
+            "even time step: {steps:?}"
+        );
+    }
+
+    #[test]
+    fn high_res_averages_within_each_point() {
+        let sine = Generator {
+            waveform_type: WaveformType::Sine,
+            freq: 1.0,
+            amplitude: 1.0,
+            ..Generator::default()
+        };
+        // One point per period: every interval averages a whole period to 0
+        let trace = acquisition(AcquisitionMode::HighRes).sample(&sine, 0.25, 4.25, 5, None);
+        assert_eq!(trace.len(), 5, "one sample per point");
+        for s in &trace {
+            assert!(s.v.abs() < 1e-12, "mean over a period at {}: {}", s.t, s.v);
+        }
+    }
+
+    #[test]
+    fn record_follows_depth_and_rate() {
+        let mut acquisition = Acquisition {
+            memory_depth: 1001,
+            auto_rate: false,
+            sample_rate: 1e3,
+            ..Acquisition::default()
+        };
+        assert_eq!(acquisition.record(10.0), (-0.5, 0.5), "fixed rate");
+
+        acquisition.auto_rate = true;
+        assert!(
+            (acquisition.rate(0.01) - 100e3).abs() < 1e-6,
+            "automatic rate fills the screen"
+        );
+        let (start, end) = acquisition.record(0.01);
+        assert!(
+            (start + 0.005).abs() < 1e-12 && (end - 0.005).abs() < 1e-12,
+            "record spans the screen: {start}..{end}"
+        );
+    }
+
+    #[test]
+    fn oversampling_is_capped_for_deep_records() {
+        let mut acquisition = acquisition(AcquisitionMode::PeakDetect);
+        assert_eq!(acquisition.evaluations(), 10_000 * 16, "short records");
+        acquisition.memory_depth = 10_000_000;
+        assert_eq!(acquisition.evaluations(), 20_000_000, "deep records");
+        acquisition.mode = AcquisitionMode::Sample;
+        assert_eq!(acquisition.evaluations(), 10_000_000, "no oversampling");
+    }
+
+    #[test]
+    fn averages_fit_in_memory() {
+        let mut acquisition = Acquisition {
+            mode: AcquisitionMode::Average,
+            averages: 256,
+            ..Acquisition::default()
+        };
+        assert_eq!(acquisition.average_count(), 256, "short records");
+        acquisition.memory_depth = 10_000_000;
+        assert_eq!(acquisition.average_count(), 2, "deep records");
+        acquisition.memory_depth = 50_000_000;
+        assert_eq!(acquisition.average_count(), 1, "at least the latest record");
+    }
+
+    #[test]
+    fn adc_clips_and_quantizes() {
+        let adc = Adc {
+            bits: 8,
+            low: -4.0,
+            high: 4.0,
+        };
+        assert_eq!(adc.lsb(), 8.0 / 256.0, "256 levels over 8 V");
+        assert_eq!(adc.convert(0.0), 0.0, "0 V is a level");
+        assert_eq!(adc.convert(0.01), 0.0, "rounds down below half a step");
+        assert_eq!(adc.convert(0.02), adc.lsb(), "rounds up above half a step");
+        assert_eq!(adc.convert(-9.0), -4.0, "clips at the bottom rail");
+        assert_eq!(adc.convert(9.0), 4.0 - adc.lsb(), "clips at the top rail");
+    }
+
+    #[test]
+    fn high_res_resolves_below_one_step() {
+        // 1 V steps
+        let adc = Adc {
+            bits: 3,
+            low: -4.0,
+            high: 4.0,
+        };
+        let sampled = acquisition(AcquisitionMode::Sample).sample(&Ramp, 0.0, 1.0, 5, Some(adc));
+        assert!(
+            sampled.iter().all(|s| s.v.fract() == 0.0),
+            "sampling gives ADC levels: {sampled:?}"
+        );
+
+        // Half of the interval around 0.5 s rounds down, half up
+        let high_res = acquisition(AcquisitionMode::HighRes).sample(&Ramp, 0.0, 1.0, 5, Some(adc));
+        assert!(
+            (high_res[2].v - 0.5).abs() < 1e-12,
+            "high-res falls between levels: {high_res:?}"
+        );
+    }
+
+    #[test]
+    fn sampling_below_nyquist_aliases() {
+        let sine = Generator {
+            waveform_type: WaveformType::Sine,
+            freq: 900.0,
+            amplitude: 1.0,
+            ..Generator::default()
+        };
+        let adc = Adc {
+            bits: 12,
+            low: -2.0,
+            high: 2.0,
+        };
+        // 1 kS/s for 0.1 s: the 900 Hz sine folds back to 100 Hz
+        let trace = acquisition(AcquisitionMode::Sample).sample(&sine, 0.0, 0.1, 101, Some(adc));
+        let Some(measurements) = Measurements::of(&trace, EdgeThresholds::TenNinety) else {
+            panic!("no measurements of the aliased trace");
+        };
+        let Some(frequency) = measurements.get(Measurement::Frequency) else {
+            panic!("no frequency of the aliased trace");
+        };
+        assert!(
+            (frequency - 100.0).abs() < 1.0,
+            "alias frequency: {frequency} Hz"
+        );
+    }
+
+    #[test]
+    fn moving_average_survives_rounding_of_the_trigger_point() {
+        let mut averager = Averager::default();
+        let mut mean = Vec::new();
+        // The same record, triggered further and further along the acquisition clock
+        for (i, t0) in [0.1, 1_234.567_891, 98_765.432_1, 3.0e6 + 0.7]
+            .into_iter()
+            .enumerate()
+        {
+            let trace: Vec<Sample> = (0..=10)
+                .map(|k| {
+                    let t = t0 - 0.5e-3 + f64::from(k) * 0.1e-3;
+                    Sample {
+                        t: t - t0,
+                        v: i as f64,
+                    }
+                })
+                .collect();
+            mean = averager.add(trace, 16);
+        }
+        assert!(
+            mean.iter().all(|s| (s.v - 1.5).abs() < 1e-12),
+            "all four acquisitions averaged: {mean:?}"
+        );
+    }
+
+    #[test]
+    fn moving_average_of_the_last_n() {
+        let trace = |v: f64| vec![Sample { t: 0.0, v }, Sample { t: 1.0, v: 2.0 * v }];
+        let mut averager = Averager::default();
+        assert_eq!(averager.add(trace(1.0), 2), trace(1.0), "first acquisition");
+        assert_eq!(averager.add(trace(3.0), 2), trace(2.0), "mean of two");
+        assert_eq!(averager.add(trace(5.0), 2), trace(4.0), "oldest dropped");
+
+        let other_timebase = vec![Sample { t: 0.0, v: 7.0 }, Sample { t: 2.0, v: 7.0 }];
+        assert_eq!(
+            averager.add(other_timebase.clone(), 2),
+            other_timebase,
+            "a new time span restarts the average"
+        );
+    }
+}
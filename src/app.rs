@@ -1,3 +1,33 @@
+mod capture;
+mod cursors;
+mod fft;
+mod import;
+mod persistence;
+mod save;
+mod waterfall;
+mod xy;
+
+use std::sync::Arc;
+
+use capture::{DisplayList, ImageFormat};
+use cursors::Cursors;
+use fft::Fft;
+use import::{ImportSettings, PendingImport};
+use persistence::Persistence;
+use waterfall::Waterfall;
+use xy::Xy;
+
+use crate::acquisition::{
+    Acquisition, AcquisitionMode, Adc, MEMORY_DEPTHS, RESOLUTIONS, SAMPLE_RATES,
+};
+use crate::channel::{Channel, Coupling, channel_removed, unused_number};
+use crate::export::{Export, ExportChannel, ExportRange, describe_source};
+use crate::interpolation::{Interpolation, sinc};
+use crate::math::{Math, Operator};
+use crate::measure::{EdgeThresholds, Measurement, MeasurementStatistics, Measurements, format_si};
+use crate::signal::{Expression, Generator, Recording, Sample, WaveformType};
+use crate::trigger::{Slope, SweepMode, Trigger};
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -7,19 +37,65 @@ pub struct TemplateApp {
 
     #[serde(skip)] // This how you opt-out of serialization of a field
     value: f32,
-    freq: f32,
-    amplitude: f32,
-    scale_div_volt: f32,
     scale_div_ms: f32,
+    channels: Vec<Channel>,
+    trigger: Trigger,
+    acquisition: Acquisition,
+    cursors: Cursors,
+    fft: Fft,
+    waterfall: Waterfall,
+    xy: Xy,
+    persistence: Persistence,
+    import_settings: ImportSettings,
+    export_range: ExportRange,
+    /// Show the timebase and channel settings under the scope, and in saved images.
+    annotate: bool,
+    /// Reference levels for rise and fall time measurements.
+    edge_thresholds: EdgeThresholds,
+    show_statistics: bool,
+    /// Mark each sample of the traces when they are far enough apart to tell.
+    show_sample_dots: bool,
+    /// How traces are drawn between samples that are further apart than a pixel or two.
+    interpolation: Interpolation,
 
+    /// Free-running acquisition. When false the last acquisition stays on screen.
     #[serde(skip)]
     running: bool,
+    /// Stop after the next acquisition.
     #[serde(skip)]
-    waveform: Vec<f32>,
+    single_shot: bool,
+    /// Acquisition clock: seconds of signal time that have passed while running.
+    ///
+    /// Sources are sampled at `t + phase`, so advancing it makes the trace scroll.
     #[serde(skip)]
     phase: f64,
+    /// Acquisition-clock time of the last trigger event, for holdoff.
+    #[serde(skip)]
+    last_trigger: Option<f64>,
+    #[serde(skip)]
+    trigger_status: TriggerStatus,
+    /// Acquisition-clock time of the last acquisition attempt.
+    #[serde(skip)]
+    acquired_at: f64,
+    /// Settings the last acquisition attempt was made with. `None` to acquire right away.
+    #[serde(skip)]
+    acquired_with: Option<AcquisitionSettings>,
+    #[serde(skip)]
+    pending_import: Option<PendingImport>,
+    /// Time range (s) shown on screen, relative to the trigger point.
+    #[serde(skip)]
+    visible_window: (f64, f64),
+    /// Measurements of each channel's trace over the 10 divisions, updated every frame.
+    #[serde(skip)]
+    measurements: Vec<Option<Measurements>>,
+    /// Each channel's measurements over all acquisitions since the last reset.
+    #[serde(skip)]
+    statistics: Vec<MeasurementStatistics>,
+    #[serde(skip)]
+    capture_request: Option<ImageFormat>,
+    /// Screen area saved as an image: the scope and its annotation footer.
     #[serde(skip)]
-    waveform_type: WaveformType,
+    capture_rect: egui::Rect,
     #[serde(skip)]
     zoom: f32,
     #[serde(skip)]
@@ -28,11 +104,84 @@ pub struct TemplateApp {
     pan_offset_y: f32,
 }
 
-#[derive(PartialEq, Eq, serde::Deserialize, serde::Serialize, Clone, Copy)]
-pub enum WaveformType {
-    Sine,
-    Square,
-    Triangle,
+/// Signal evaluations per second of acquisition-clock time that the records of all
+/// channels may take together. Deeper records are reacquired less often than every frame.
+const EVALUATIONS_PER_SECOND: f64 = 50e6;
+
+/// Most points of signal the trigger scans for an event before the record is sampled.
+const TRIGGER_SCAN_POINTS: usize = 10_000;
+
+/// Height (points) of the annotation footer under the scope.
+const FOOTER_HEIGHT: f32 = 44.0;
+
+/// Measurements shown in the annotation footer.
+const FOOTER_MEASUREMENTS: [Measurement; 3] = [
+    Measurement::PeakToPeak,
+    Measurement::Rms,
+    Measurement::Frequency,
+];
+
+/// Everything an acquisition depends on, to reacquire right away when any of it changes.
+#[derive(PartialEq)]
+struct AcquisitionSettings {
+    acquisition: Acquisition,
+    trigger: Trigger,
+    scale_div_ms: f32,
+    channels: Vec<ChannelSettings>,
+}
+
+/// The settings of a channel that its trace depends on.
+struct ChannelSettings {
+    enabled: bool,
+    source: Generator,
+    recording: Option<Arc<Recording>>,
+    math: Option<Math>,
+    volts_per_div: f64,
+    offset: f64,
+    coupling: Coupling,
+}
+
+impl ChannelSettings {
+    fn of(channel: &Channel) -> Self {
+        Self {
+            enabled: channel.enabled,
+            source: channel.source.clone(),
+            recording: channel.recording.clone(),
+            math: channel.math,
+            volts_per_div: channel.volts_per_div,
+            offset: channel.offset,
+            coupling: channel.coupling,
+        }
+    }
+}
+
+impl PartialEq for ChannelSettings {
+    /// Recordings are compared by identity: a new import is a change.
+    fn eq(&self, other: &Self) -> bool {
+        let same_recording = match (&self.recording, &other.recording) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        same_recording
+            && self.enabled == other.enabled
+            && self.source == other.source
+            && self.math == other.math
+            && self.volts_per_div == other.volts_per_div
+            && self.offset == other.offset
+            && self.coupling == other.coupling
+    }
+}
+
+/// Outcome of the most recent acquisition attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TriggerStatus {
+    Triggered,
+
+    /// No trigger event; free-running because the sweep mode is Auto.
+    Auto,
+
+    /// No trigger event; keeping the previous acquisition.
+    Waiting,
 }
 
 impl Default for TemplateApp {
@@ -41,14 +190,41 @@ impl Default for TemplateApp {
             // Example stuff:
             label: "Hello World!".to_owned(),
             value: 2.7,
-            amplitude: 5.0,
-            freq: 250.0,
             scale_div_ms: 1.0,
-            scale_div_volt: 1.0,
+            channels: vec![
+                Channel::new(0),
+                Channel {
+                    enabled: false,
+                    ..Channel::new(1)
+                },
+            ],
+            trigger: Trigger::default(),
+            acquisition: Acquisition::default(),
+            cursors: Cursors::default(),
+            fft: Fft::default(),
+            waterfall: Waterfall::default(),
+            xy: Xy::default(),
+            persistence: Persistence::default(),
+            import_settings: ImportSettings::default(),
+            export_range: ExportRange::Visible,
+            annotate: false,
+            edge_thresholds: EdgeThresholds::TenNinety,
+            show_statistics: false,
+            show_sample_dots: false,
+            interpolation: Interpolation::Linear,
             running: true,
-            waveform: vec![0.0; 512],
+            single_shot: false,
             phase: 0.0,
-            waveform_type: WaveformType::Sine,
+            last_trigger: None,
+            trigger_status: TriggerStatus::Waiting,
+            acquired_at: 0.0,
+            acquired_with: None,
+            pending_import: None,
+            visible_window: (0.0, 0.0),
+            measurements: Vec::new(),
+            statistics: Vec::new(),
+            capture_request: None,
+            capture_rect: egui::Rect::NOTHING,
             zoom: 1.0,
             pan_offset_x: 0.0,
             pan_offset_y: 0.0,
@@ -80,66 +256,222 @@ impl eframe::App for TemplateApp {
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.running {
+            self.phase += f64::from(ctx.input(|i| i.stable_dt));
+            ctx.request_repaint();
+        }
+        self.screenshot(ctx);
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
-                let is_web = cfg!(target_arch = "wasm32");
-                if !is_web {
-                    ui.menu_button("File", |ui| {
-                        if ui.button("Quit").clicked() {
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
-                    });
-                    ui.add_space(16.0);
-                }
+                ui.menu_button("File", |ui| self.file_menu(ui));
+                ui.add_space(16.0);
                 egui::widgets::global_theme_preference_buttons(ui);
             });
         });
 
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
-            ui.heading("Virtual Oscilloscope");
+            self.side_panel(ui);
+        });
 
-            ui.separator();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.scope(ui);
 
-            ui.label("Waveform:");
-            egui::ComboBox::from_id_salt("waveform_type")
-                .selected_text(match self.waveform_type {
-                    WaveformType::Sine => "Sine",
-                    WaveformType::Square => "Square",
-                    WaveformType::Triangle => "Triangle",
-                })
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut self.waveform_type, WaveformType::Sine, "Sine");
-                    ui.selectable_value(&mut self.waveform_type, WaveformType::Square, "Square");
-                    ui.selectable_value(
-                        &mut self.waveform_type,
-                        WaveformType::Triangle,
-                        "Triangle",
+            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                powered_by_egui_and_eframe(ui);
+                egui::warn_if_debug_build(ui);
+            });
+        });
+
+        self.file_drop(ctx);
+        if let Some(pending) = &mut self.pending_import {
+            if !pending.show(ctx, &mut self.import_settings, &mut self.channels) {
+                self.pending_import = None;
+            }
+        }
+        self.statistics_window(ctx);
+    }
+}
+
+impl TemplateApp {
+    fn file_menu(&mut self, ui: &mut egui::Ui) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Open…").clicked() {
+            ui.close();
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Traces", &["csv", "txt", "wav"])
+                .pick_file()
+            {
+                self.pending_import = Some(import::read_path(&path));
+            }
+        }
+
+        ui.menu_button("Export", |ui| {
+            for range in ExportRange::ALL {
+                ui.radio_value(&mut self.export_range, range, range.name());
+            }
+            ui.separator();
+            let export = self.export();
+            ui.add_enabled_ui(!export.is_empty(), |ui| {
+                if ui.button("CSV…").clicked() {
+                    ui.close();
+                    save::save_file("scope.csv", "text/csv", export.to_csv().as_bytes());
+                }
+                if ui.button("JSON…").clicked() {
+                    ui.close();
+                    save::save_file(
+                        "scope.json",
+                        "application/json",
+                        export.to_json().as_bytes(),
                     );
-                    ui.add_space(8.0);
+                }
+            });
+        });
 
-                    if ui.button("Reset Pan").clicked() {
-                        self.pan_offset_x = 0.0;
-                        self.pan_offset_y = 0.0;
-                    }
-                });
+        ui.menu_button("Save image", |ui| {
+            ui.checkbox(&mut self.annotate, "Annotation footer");
+            ui.separator();
+            if ui.button("PNG…").clicked() {
+                ui.close();
+                self.capture_request = Some(ImageFormat::Png);
+            }
+            if ui.button("SVG…").clicked() {
+                ui.close();
+                self.capture_request = Some(ImageFormat::Svg);
+            }
+        });
 
-            ui.add_space(8.0);
+        let is_web = cfg!(target_arch = "wasm32");
+        if !is_web && ui.button("Quit").clicked() {
+            ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
 
-            ui.label("Frequency (Hz):");
-            ui.add(egui::Slider::new(&mut self.freq, 0.1..=500.0).logarithmic(true));
-            ui.label(format!("{:.1}", self.freq));
+    /// The enabled channels' traces, limited to `export_range`.
+    fn export(&self) -> Export<'_> {
+        let channels = self
+            .channels
+            .iter()
+            .filter(|channel| channel.enabled)
+            .map(|channel| {
+                let samples = match self.export_range {
+                    ExportRange::FullRecord => &channel.trace,
+                    ExportRange::Visible => self.visible_trace(channel),
+                };
+                ExportChannel {
+                    channel,
+                    samples,
+                    source: describe_source(channel, &self.channels),
+                }
+            })
+            .collect();
+        Export {
+            secs_per_div: f64::from(self.scale_div_ms) / 1000.0,
+            trigger: &self.trigger,
+            trigger_source: self.trigger_channel().map_or("", |c| c.name.as_str()),
+            channels,
+        }
+    }
 
-            ui.add_space(8.0);
+    /// Takes a screenshot for a PNG capture, and saves it once it arrives.
+    ///
+    /// The screenshot is requested the frame after the menu click, so the menu is closed.
+    fn screenshot(&mut self, ctx: &egui::Context) {
+        if self.capture_request == Some(ImageFormat::Png) {
+            self.capture_request = None;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
+        }
 
+        let screenshot = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        if let Some(image) = screenshot {
+            match capture::encode_png(&image, self.capture_rect, ctx.pixels_per_point()) {
+                Ok(png) => save::save_file("scope.png", "image/png", &png),
+                Err(err) => log::error!("Failed to encode PNG: {err}"),
+            }
+        }
+    }
+
+    /// Files dropped onto the window are offered for import (the only way to load files on
+    /// the web).
+    fn file_drop(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("file_drop_target"),
+            ));
+            let screen_rect = ctx.screen_rect();
+            painter.rect_filled(screen_rect, 0.0, egui::Color32::from_black_alpha(192));
+            painter.text(
+                screen_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop a CSV or WAV file to load it",
+                egui::TextStyle::Heading.resolve(&ctx.style()),
+                egui::Color32::WHITE,
+            );
+        }
+
+        if let Some(file) = ctx.input(|i| i.raw.dropped_files.first().cloned()) {
+            self.pending_import = Some(import::read_dropped_file(&file));
+        }
+    }
+
+    fn side_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Virtual Oscilloscope");
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let (run_text, run_color) = if self.running {
+                ("Stop", egui::Color32::from_rgb(200, 60, 60))
+            } else {
+                ("Run", egui::Color32::from_rgb(60, 160, 60))
+            };
+            if ui
+                .add(egui::Button::new(
+                    egui::RichText::new(run_text).color(run_color),
+                ))
+                .clicked()
+            {
+                self.running = !self.running;
+                self.single_shot = false;
+                self.acquired_with = None;
+            }
+            if ui.button("Single").clicked() {
+                self.running = true;
+                self.single_shot = true;
+                self.acquired_with = None;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(if !self.running {
+                "Stopped"
+            } else {
+                match self.trigger_status {
+                    TriggerStatus::Triggered => "Trig'd",
+                    TriggerStatus::Auto => "Auto",
+                    TriggerStatus::Waiting => "Waiting for trigger",
+                }
+            });
+            ui.separator();
+            ui.label(self.acquisition.status(self.screen_time()));
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
             ui.label("Zoom:");
             ui.add(egui::Slider::new(&mut self.zoom, 1.0..=10.0).logarithmic(true));
             ui.label(format!("{:.1}x", self.zoom));
 
-            ui.add_space(8.0);
-
-            ui.label("Amplitude (V):");
-            ui.add(egui::Slider::new(&mut self.amplitude, 0.1..=200.0));
-            ui.label(format!("{:.2}", self.amplitude));
+            if ui.button("Reset Pan").clicked() {
+                self.pan_offset_x = 0.0;
+                self.pan_offset_y = 0.0;
+            }
 
             ui.add_space(8.0);
 
@@ -149,245 +481,1361 @@ impl eframe::App for TemplateApp {
 
             ui.add_space(8.0);
 
-            ui.label("Volts/div:");
-            ui.add(egui::Slider::new(&mut self.scale_div_volt, 0.1..=200.0));
-            ui.label(format!("{:.2}", self.scale_div_volt));
-        });
+            let names: Vec<String> = self.channels.iter().map(|c| c.name.clone()).collect();
+            let mut remove = None;
+            for (index, channel) in self.channels.iter_mut().enumerate() {
+                if channel_panel(ui, index, channel, &names, self.phase) {
+                    remove = Some(index);
+                }
+            }
+            if let Some(index) = remove {
+                self.remove_channel(index);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add channel").clicked() {
+                    let number = unused_number(&self.channels, false);
+                    self.channels.push(Channel::new(number));
+                }
+                if ui.button("Add math channel").clicked() {
+                    let number = unused_number(&self.channels, true);
+                    self.channels
+                        .push(Channel::new_math(number, Math::default()));
+                }
+            });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Draw waveform with square grid using all available space
-            let (rect, response) = ui.allocate_exact_size(
-                egui::vec2(ui.available_width(), ui.available_height()),
-                egui::Sense::drag(),
-            );
+            ui.add_space(8.0);
 
-            // Handle scroll wheel for zoom
-            if response.hovered() {
-                let scroll = ui.input(|i| i.raw_scroll_delta.y);
-                if scroll != 0.0 {
-                    // Positive scroll.y is up (zoom in), negative is down (zoom out)
-                    let zoom_speed = 1.1;
-                    if scroll > 0.0 {
-                        self.zoom = (self.zoom * zoom_speed).min(10.0);
-                    } else {
-                        self.zoom = (self.zoom / zoom_speed).max(1.0);
-                    }
-                    ctx.request_repaint();
-                }
+            self.acquisition_panel(ui);
+            self.trigger_panel(ui);
+            self.cursors.panel(ui, &self.channels);
+            self.measurements_panel(ui);
+            self.fft.panel(ui, &self.channels);
+            self.waterfall.panel(ui, &self.channels);
+            self.persistence.panel(ui);
+            self.xy.panel(ui, &self.channels);
+        });
+    }
+
+    /// Removes a channel, keeping everything that refers to channels by index in step.
+    fn remove_channel(&mut self, index: usize) {
+        self.channels.remove(index);
+        if index < self.statistics.len() {
+            self.statistics.remove(index);
+        }
+        for channel in &mut self.channels {
+            if let Some(math) = &mut channel.math {
+                math.channel_removed(index);
             }
+        }
+        for source in [
+            &mut self.trigger.source,
+            &mut self.cursors.source,
+            &mut self.fft.source,
+            &mut self.waterfall.source,
+            &mut self.xy.x,
+            &mut self.xy.y,
+        ] {
+            channel_removed(source, index);
+        }
+        // The history's points may have come from the removed channel
+        self.xy.clear();
+    }
+
+    /// Time (s) across the 10 divisions of the timebase.
+    fn screen_time(&self) -> f64 {
+        f64::from(ScreenGrid::HDIVS) * f64::from(self.scale_div_ms) / 1000.0
+    }
 
-            // Handle mouse drag for panning
-            if response.dragged() {
-                let delta = response.drag_delta();
-                self.pan_offset_x += delta.x;
-                self.pan_offset_y += delta.y;
-                ctx.request_repaint();
+    fn acquisition_panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Acquisition").show(ui, |ui| {
+            egui::ComboBox::from_label("Mode")
+                .selected_text(self.acquisition.mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in AcquisitionMode::ALL {
+                        ui.selectable_value(&mut self.acquisition.mode, mode, mode.name());
+                    }
+                });
+            ui.add_enabled(
+                self.acquisition.mode == AcquisitionMode::Average,
+                egui::Slider::new(&mut self.acquisition.averages, 2..=256)
+                    .logarithmic(true)
+                    .text("Averages"),
+            );
+            let averages = self.acquisition.average_count();
+            if self.acquisition.mode == AcquisitionMode::Average
+                && averages < self.acquisition.averages
+            {
+                ui.label(format!("Limited to {averages} by the memory depth"));
             }
+            egui::ComboBox::from_label("Memory depth")
+                .selected_text(format_si(self.acquisition.memory_depth as f64, "S"))
+                .show_ui(ui, |ui| {
+                    for depth in MEMORY_DEPTHS {
+                        let text = format_si(depth as f64, "S");
+                        ui.selectable_value(&mut self.acquisition.memory_depth, depth, text);
+                    }
+                });
+            ui.checkbox(&mut self.acquisition.auto_rate, "Automatic sample rate")
+                .on_hover_text("Spread the record over the 10 divisions of the timebase");
+            ui.add_enabled_ui(!self.acquisition.auto_rate, |ui| {
+                egui::ComboBox::from_label("Sample rate")
+                    .selected_text(format_si(self.acquisition.sample_rate, "S/s"))
+                    .show_ui(ui, |ui| {
+                        for rate in SAMPLE_RATES {
+                            let text = format_si(rate, "S/s");
+                            ui.selectable_value(&mut self.acquisition.sample_rate, rate, text);
+                        }
+                    });
+            });
+            let (start, end) = self.acquisition.record(self.screen_time());
+            ui.label(format!("Record length: {}", format_si(end - start, "s")));
+            egui::ComboBox::from_label("ADC")
+                .selected_text(
+                    self.acquisition
+                        .resolution
+                        .map_or_else(|| "Ideal".to_owned(), |bits| format!("{bits} bit")),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.acquisition.resolution, None, "Ideal");
+                    for bits in RESOLUTIONS {
+                        let text = format!("{bits} bit");
+                        ui.selectable_value(&mut self.acquisition.resolution, Some(bits), text);
+                    }
+                })
+                .response
+                .on_hover_text("Quantize to this many bits over the 8 divisions, clipping beyond");
+            egui::ComboBox::from_label("Interpolation")
+                .selected_text(self.interpolation.name())
+                .show_ui(ui, |ui| {
+                    for interpolation in Interpolation::ALL {
+                        ui.selectable_value(
+                            &mut self.interpolation,
+                            interpolation,
+                            interpolation.name(),
+                        );
+                    }
+                });
+            ui.checkbox(&mut self.show_sample_dots, "Show sample dots")
+                .on_hover_text("Mark each sample, to see how the trace is drawn through them");
+        });
+    }
 
-            let painter = ui.painter_at(rect);
-
-            let w = rect.width();
-            let h = rect.height();
-            let left = rect.left();
-            let top = rect.top();
-
-            // Fixed grid: 10 horizontal, 8 vertical divisions
-            let hdivs = 10.0_f32;
-            let vdivs = 8.0_f32;
-
-            // Always use a square cell size, scaled by zoom
-            let cell_size = (w / hdivs).min(h / vdivs) * self.zoom;
-
-            // Define the screen position of (0,0): center of panel plus pan offset
-            let origin_x = left + w / 2.0 + self.pan_offset_x;
-            let origin_y = top + h / 2.0 + self.pan_offset_y;
-
-            // Adjust scaling for waveform
-
-            // Draw square grid
-            let grid_color = egui::Color32::from_gray(60);
-            let strong_grid_color = egui::Color32::from_gray(90);
-            let stroke = egui::Stroke::new(1.0, grid_color);
-            let strong_stroke = egui::Stroke::new(1.5, strong_grid_color);
-
-            // Infinite grid: draw enough lines to fill the visible area, based on pan and zoom
-            // Compute the visible range in grid coordinates, centered at (0,0) = (origin_x, origin_y)
-            let min_x = ((left - origin_x) / cell_size).floor() as isize - 2;
-            let max_x = ((left + w - origin_x) / cell_size).ceil() as isize + 2;
-            let min_y = ((top - origin_y) / cell_size).floor() as isize - 2;
-            let max_y = ((top + h - origin_y) / cell_size).ceil() as isize + 2;
-
-            // Vertical grid lines (x = 0 is the y-axis)
-            for i in min_x..=max_x {
-                let x = origin_x + (i as f32) * cell_size;
-                let s = if i == 0 { &strong_stroke } else { &stroke };
-                painter.line_segment([egui::pos2(x, top), egui::pos2(x, top + h)], *s);
-
-                // Minor increment ticks along the main X axis (center horizontal line)
-                if i == 0 {
-                    let minor_ticks = 10;
-                    let minor_tick_len = cell_size * 0.10;
-                    let major_tick_len = cell_size * 0.22;
-                    let tick_color = egui::Color32::from_gray(140);
-                    for div in min_y..=max_y {
-                        let div_top = origin_y + (div as f32) * cell_size;
-                        // Major tick at the division, but skip if at axis (0,0) to avoid double-drawing
-                        if !(i == 0 && div == 0) {
-                            painter.line_segment(
-                                [
-                                    egui::pos2(x - major_tick_len / 2.0, div_top),
-                                    egui::pos2(x + major_tick_len / 2.0, div_top),
-                                ],
-                                egui::Stroke::new(1.5, tick_color),
-                            );
+    fn trigger_panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Trigger")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::ComboBox::from_label("Source")
+                    .selected_text(
+                        self.channels
+                            .get(self.trigger.source)
+                            .map_or("", |channel| channel.name.as_str()),
+                    )
+                    .show_ui(ui, |ui| {
+                        // Math channels are computed after the trigger point is found
+                        for (index, channel) in self.channels.iter().enumerate() {
+                            if channel.math.is_none() {
+                                ui.selectable_value(&mut self.trigger.source, index, &channel.name);
+                            }
                         }
-                        // Minor ticks between divisions
-                        for m in 1..minor_ticks {
-                            let frac = m as f32 / minor_ticks as f32;
-                            let y_tick = div_top + frac * cell_size;
-                            painter.line_segment(
-                                [
-                                    egui::pos2(x - minor_tick_len / 2.0, y_tick),
-                                    egui::pos2(x + minor_tick_len / 2.0, y_tick),
-                                ],
-                                egui::Stroke::new(1.0, tick_color),
-                            );
+                    });
+                egui::ComboBox::from_label("Mode")
+                    .selected_text(self.trigger.mode.name())
+                    .show_ui(ui, |ui| {
+                        for mode in [SweepMode::Auto, SweepMode::Normal, SweepMode::Single] {
+                            ui.selectable_value(&mut self.trigger.mode, mode, mode.name());
                         }
+                    });
+                egui::ComboBox::from_label("Slope")
+                    .selected_text(self.trigger.slope.name())
+                    .show_ui(ui, |ui| {
+                        for slope in [Slope::Rising, Slope::Falling, Slope::Either] {
+                            ui.selectable_value(&mut self.trigger.slope, slope, slope.name());
+                        }
+                    });
+                let volts_per_div = self.trigger_channel().map_or(1.0, |c| c.volts_per_div);
+                ui.horizontal(|ui| {
+                    ui.label("Level:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.trigger.level)
+                            .speed(0.01 * volts_per_div)
+                            .suffix(" V"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Holdoff:");
+                    let mut holdoff_ms = self.trigger.holdoff * 1000.0;
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut holdoff_ms)
+                                .speed(0.1)
+                                .range(0.0..=10_000.0)
+                                .suffix(" ms"),
+                        )
+                        .changed()
+                    {
+                        self.trigger.holdoff = holdoff_ms / 1000.0;
                     }
-                }
+                });
+            });
+    }
 
-                // Draw small ticks only on the main X axis (center horizontal line)
-                if i == 0 {
-                    let y = origin_y;
-                    let tick_len = cell_size * 0.25;
-                    painter.line_segment(
-                        [
-                            egui::pos2(x, y - tick_len / 2.0),
-                            egui::pos2(x, y + tick_len / 2.0),
-                        ],
-                        egui::Stroke::new(2.0, egui::Color32::WHITE),
-                    );
+    /// A table of measurements, one column per enabled channel.
+    fn measurements_panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Measurements")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Rise/fall:");
+                    egui::ComboBox::from_id_salt("edge_thresholds")
+                        .selected_text(self.edge_thresholds.name())
+                        .show_ui(ui, |ui| {
+                            for thresholds in EdgeThresholds::ALL {
+                                ui.selectable_value(
+                                    &mut self.edge_thresholds,
+                                    thresholds,
+                                    thresholds.name(),
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.toggle_value(&mut self.show_statistics, "Statistics");
+                    if ui.button("Reset").clicked() {
+                        self.statistics.clear();
+                    }
+                });
+
+                let channels: Vec<(&Channel, Option<&Measurements>)> = self
+                    .channels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, channel)| channel.enabled)
+                    .map(|(index, channel)| {
+                        (
+                            channel,
+                            self.measurements.get(index).and_then(Option::as_ref),
+                        )
+                    })
+                    .collect();
+                egui::Grid::new("measurements")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        for (channel, _) in &channels {
+                            ui.colored_label(channel.color, &channel.name);
+                        }
+                        ui.end_row();
+
+                        for measurement in Measurement::ALL {
+                            ui.label(measurement.name());
+                            for (channel, measurements) in &channels {
+                                ui.monospace(format_measurement(
+                                    *measurements,
+                                    measurement,
+                                    channel.unit(),
+                                ));
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Running statistics of every measurement that has been made since the last reset.
+    fn statistics_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_statistics;
+        egui::Window::new("Measurement statistics")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if ui.button("Reset").clicked() {
+                    self.statistics.clear();
                 }
-            }
-            // Horizontal grid lines (y = 0 is the x-axis)
-            for j in min_y..=max_y {
-                let y = origin_y + (j as f32) * cell_size;
-                let s = if j == 0 { &strong_stroke } else { &stroke };
-                painter.line_segment([egui::pos2(left, y), egui::pos2(left + w, y)], *s);
-
-                // Minor increment ticks along the main Y axis (center vertical line)
-                if j == 0 {
-                    let minor_ticks = 10;
-                    let minor_tick_len = cell_size * 0.10;
-                    let major_tick_len = cell_size * 0.22;
-                    let tick_color = egui::Color32::from_gray(140);
-                    for div in min_x..=max_x {
-                        let div_left = origin_x + (div as f32) * cell_size;
-                        // Major tick at the division, but skip if at axis (0,0) to avoid double-drawing
-                        if !(j == 0 && div == 0) {
-                            painter.line_segment(
-                                [
-                                    egui::pos2(div_left, y - major_tick_len / 2.0),
-                                    egui::pos2(div_left, y + major_tick_len / 2.0),
-                                ],
-                                egui::Stroke::new(1.5, tick_color),
-                            );
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("statistics").striped(true).show(ui, |ui| {
+                        for heading in ["", "", "Current", "Mean", "Min", "Max", "Std dev", "Count"]
+                        {
+                            ui.strong(heading);
                         }
-                        // Minor ticks between divisions
-                        for m in 1..minor_ticks {
-                            let frac = m as f32 / minor_ticks as f32;
-                            let x_tick = div_left + frac * cell_size;
-                            painter.line_segment(
-                                [
-                                    egui::pos2(x_tick, y - minor_tick_len / 2.0),
-                                    egui::pos2(x_tick, y + minor_tick_len / 2.0),
-                                ],
-                                egui::Stroke::new(1.0, tick_color),
-                            );
+                        ui.end_row();
+
+                        for (channel, statistics) in self.channels.iter().zip(&self.statistics) {
+                            if !channel.enabled {
+                                continue;
+                            }
+                            for measurement in Measurement::ALL {
+                                let stats = statistics.get(measurement);
+                                let (Some(mean), Some(std_dev)) = (stats.mean(), stats.std_dev())
+                                else {
+                                    continue;
+                                };
+                                ui.colored_label(channel.color, &channel.name);
+                                ui.label(measurement.name());
+                                for value in [stats.current, mean, stats.min, stats.max, std_dev] {
+                                    ui.monospace(measurement.format(value, channel.unit()));
+                                }
+                                ui.monospace(stats.count.to_string());
+                                ui.end_row();
+                            }
                         }
-                    }
+                    });
+                });
+            });
+        self.show_statistics = open;
+    }
+
+    /// The oscilloscope screen: grid, pan/zoom handling and the trace.
+    fn scope(&mut self, ui: &mut egui::Ui) {
+        // Draw waveform with square grid using all available space
+        let (full_rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), ui.available_height()),
+            egui::Sense::drag(),
+        );
+        let (rect, footer_rect) = if self.annotate {
+            full_rect.split_top_bottom_at_y(full_rect.bottom() - FOOTER_HEIGHT)
+        } else {
+            (full_rect, egui::Rect::NOTHING)
+        };
+        self.capture_rect = full_rect;
+
+        // Time view, spectrum and waterfall stacked top to bottom
+        let time_view = !self.fft.enabled || !self.fft.spectrum_only;
+        let views = [time_view, self.fft.enabled, self.waterfall.enabled];
+        let mut rows = stack(rect, views.iter().filter(|&&shown| shown).count());
+        let [time_rect, spectrum_rect, waterfall_rect] = views.map(|shown| {
+            shown
+                .then(|| rows.next())
+                .flatten()
+                .unwrap_or(egui::Rect::NOTHING)
+        });
+        // Without the time view its grid still sets the acquisition window
+        let rect = if time_view { time_rect } else { rect };
+        if time_view {
+            self.pan_and_zoom(ui, &response);
+        }
+
+        let mut display = DisplayList::new(ui.painter_at(rect));
+        let grid = ScreenGrid::new(
+            rect,
+            self.zoom,
+            egui::vec2(self.pan_offset_x, self.pan_offset_y),
+        );
+
+        // The record is set by the acquisition settings alone; the screen only shows part of it
+        let secs_per_div = f64::from(self.scale_div_ms) / 1000.0;
+        self.visible_window = (
+            grid.time_at(rect.left(), secs_per_div),
+            grid.time_at(rect.right(), secs_per_div),
+        );
+        let mut acquired = false;
+        if self.running && self.acquisition_due() {
+            acquired = self.acquire(
+                self.acquisition.record(self.screen_time()),
+                self.acquisition.memory_depth,
+            );
+        }
+
+        self.measure(acquired);
+
+        if time_view && self.xy.enabled {
+            draw_grid(&mut display, &grid);
+            self.xy.update(&self.channels, acquired);
+            self.xy.show(&mut display, &grid, &self.channels);
+        } else if time_view {
+            draw_grid(&mut display, &grid);
+            if self.persistence.enabled {
+                if self.running {
+                    self.persistence.fade(ui.input(|i| f64::from(i.stable_dt)));
                 }
+                self.persistence.update(
+                    &grid,
+                    secs_per_div,
+                    &self.channels,
+                    self.interpolation,
+                    acquired,
+                );
+                self.persistence
+                    .show(ui.ctx(), &mut display, rect, &self.channels);
+            }
+            self.draw_traces(&mut display, &grid, secs_per_div);
+            self.trigger_level_marker(ui, &mut display, &grid);
+            self.cursors
+                .show(ui, &mut display, &grid, secs_per_div, &self.channels);
+        }
+        if self.fft.enabled {
+            self.fft.update(&self.channels, acquired);
+            self.fft.show(&mut display, spectrum_rect, &self.channels);
+        }
+        if self.waterfall.enabled {
+            self.waterfall.update(&self.channels, acquired);
+            self.waterfall.show(ui.ctx(), &mut display, waterfall_rect);
+        }
 
-                // Draw small ticks only on the main Y axis (center vertical line)
-                if j == 0 {
-                    let x = origin_x;
-                    let tick_len = cell_size * 0.25;
-                    painter.line_segment(
-                        [
-                            egui::pos2(x - tick_len / 2.0, y),
-                            egui::pos2(x + tick_len / 2.0, y),
-                        ],
-                        egui::Stroke::new(2.0, egui::Color32::WHITE),
-                    );
+        if self.annotate {
+            display.set_clip_rect(footer_rect);
+            self.annotation_footer(&mut display, footer_rect);
+        }
+
+        display.paint();
+        if self.capture_request == Some(ImageFormat::Svg) {
+            self.capture_request = None;
+            let svg = display.to_svg(full_rect, ui.visuals().panel_fill);
+            save::save_file("scope.svg", "image/svg+xml", svg.as_bytes());
+        }
+    }
+
+    /// Scroll wheel zooms, dragging pans.
+    fn pan_and_zoom(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        // Handle scroll wheel for zoom
+        if response.hovered() {
+            let scroll = ui.input(|i| i.raw_scroll_delta.y);
+            if scroll != 0.0 {
+                // Positive scroll.y is up (zoom in), negative is down (zoom out)
+                let zoom_speed = 1.1;
+                if scroll > 0.0 {
+                    self.zoom = (self.zoom * zoom_speed).min(10.0);
+                } else {
+                    self.zoom = (self.zoom / zoom_speed).max(1.0);
                 }
+                ui.ctx().request_repaint();
             }
+        }
+
+        // Handle mouse drag for panning
+        if response.dragged() {
+            let delta = response.drag_delta();
+            self.pan_offset_x += delta.x;
+            self.pan_offset_y += delta.y;
+            ui.ctx().request_repaint();
+        }
+    }
 
-            // Draw border
-            // No border rectangle needed for infinite grid
-
-            // Draw waveform
-            let volts_per_div = self.scale_div_volt;
-            let freq = self.freq;
-            let amplitude = self.amplitude;
-            let ms_per_div = self.scale_div_ms;
-
-            // Calculate number of points based on screen width (like infinite grid)
-            // Use every 2 pixels for good performance while maintaining smooth curves
-            let pixel_step = 2.0;
-            let visible_points = (w / pixel_step) as usize;
-            let mut points: Vec<egui::Pos2> = Vec::with_capacity(visible_points);
-
-            // Calculate the visible x range in screen coordinates
-            let x_start = left;
-            let x_end = left + w;
-
-            for i in 0..visible_points {
-                // Calculate screen x position
-                let x = x_start + (i as f32) * pixel_step;
-                // Calculate distance from (0,0) marker in pixels
-                let dx = x - origin_x;
-                // Convert to grid divisions
-                let dx_grid = dx / cell_size;
-                // Convert to time in ms (center is t=0)
-                let t_ms = dx_grid * ms_per_div;
-                // Convert ms to seconds
-                let t = t_ms / 1000.0;
-                // Calculate phase for this time
-                let phase = 2.0 * std::f32::consts::PI * freq * t;
-                // Evaluate waveform at this phase
-                let v = match self.waveform_type {
-                    WaveformType::Sine => amplitude * phase.sin(),
-                    WaveformType::Square => {
-                        // Square wave: positive when sin of phase is positive
-                        if phase.sin() >= 0.0 {
-                            amplitude
-                        } else {
-                            -amplitude
+    fn draw_traces(&self, display: &mut DisplayList, grid: &ScreenGrid, secs_per_div: f64) {
+        for channel in self.channels.iter().filter(|c| c.enabled) {
+            let samples = screen_points(channel, grid, secs_per_div, Interpolation::Linear);
+            // Dots closer than a few pixels would only thicken the line
+            let sparse = samples.len() as f32 <= grid.rect.width() / 4.0;
+            if self.interpolation == Interpolation::None || (self.show_sample_dots && sparse) {
+                let radius = if sparse { 3.0 } else { 1.0 };
+                for &point in &samples {
+                    display.add(egui::Shape::circle_filled(point, radius, channel.color));
+                }
+            }
+            ground_marker(display, grid, channel);
+            let points = match self.interpolation {
+                Interpolation::None => continue,
+                Interpolation::Linear => samples,
+                Interpolation::Sinc => {
+                    screen_points(channel, grid, secs_per_div, Interpolation::Sinc)
+                }
+            };
+            display.add(egui::Shape::line(
+                points,
+                egui::Stroke::new(2.0, channel.color),
+            ));
+        }
+    }
+
+    /// Timebase, trigger and channel settings on the first row, measurements on the second.
+    fn annotation_footer(&self, display: &mut DisplayList, rect: egui::Rect) {
+        let font = egui::FontId::monospace(12.0);
+        let text_color = egui::Color32::from_gray(200);
+        let mut pos = rect.left_top() + egui::vec2(8.0, 6.0);
+
+        let trigger = self.trigger_channel().map_or_else(String::new, |channel| {
+            format!(
+                "Trig {} {} {:.3} V",
+                channel.name,
+                self.trigger.slope.name(),
+                self.trigger.level
+            )
+        });
+        let mut items = vec![
+            (format!("{:.2} ms/div", self.scale_div_ms), text_color),
+            (self.acquisition.status(self.screen_time()), text_color),
+            (trigger, text_color),
+        ];
+        for channel in self.channels.iter().filter(|c| c.enabled) {
+            items.push((
+                format!(
+                    "{} {:.2} {}/div {}",
+                    channel.name,
+                    channel.volts_per_div,
+                    channel.unit(),
+                    channel.coupling.name()
+                ),
+                channel.color,
+            ));
+        }
+
+        let mut measurements = Vec::new();
+        for (index, channel) in self.channels.iter().enumerate() {
+            if !channel.enabled {
+                continue;
+            }
+            let results = self.measurements.get(index).and_then(Option::as_ref);
+            let values: Vec<String> = FOOTER_MEASUREMENTS
+                .iter()
+                .map(|&m| {
+                    format!(
+                        "{} {}",
+                        m.name(),
+                        format_measurement(results, m, channel.unit())
+                    )
+                })
+                .collect();
+            measurements.push((
+                format!("{} {}", channel.name, values.join("  ")),
+                channel.color,
+            ));
+        }
+
+        for row in [items, measurements] {
+            let mut x = pos.x;
+            for (text, color) in row {
+                let galley = display.layout(text, font.clone(), color);
+                let width = galley.size().x;
+                display.galley(egui::pos2(x, pos.y), galley);
+                x += width + 24.0;
+            }
+            pos.y += font.size + 4.0;
+        }
+    }
+
+    /// Computes the math channels' traces, in channel order, so a math channel can use the
+    /// math channels before it.
+    fn compute_math(&mut self) {
+        for index in 0..self.channels.len() {
+            let Some(math) = self.channels[index].math else {
+                continue;
+            };
+            let operand = |i: usize| self.channels.get(i).map_or(&[][..], |c| &c.trace[..]);
+            let mut trace = math.apply(operand(math.a), operand(math.b));
+            let channel = &mut self.channels[index];
+            channel.coupling.apply(&mut trace);
+            channel.trace = trace;
+        }
+    }
+
+    /// Measures the 10 divisions of every trace around the trigger point, adding to the
+    /// statistics if the traces are from a new acquisition.
+    ///
+    /// The window is set by the timebase alone, so the size of the scope area, zoom and pan
+    /// do not change the results.
+    fn measure(&mut self, acquired: bool) {
+        let half = self.screen_time() / 2.0;
+        self.measurements = self
+            .channels
+            .iter()
+            .map(|channel| {
+                let trace = trace_between(&channel.trace, (-half, half));
+                Measurements::of(trace, self.edge_thresholds)
+            })
+            .collect();
+        if acquired {
+            self.statistics
+                .resize_with(self.channels.len(), Default::default);
+            for (statistics, measurements) in self.statistics.iter_mut().zip(&self.measurements) {
+                if let Some(measurements) = measurements {
+                    statistics.add(measurements);
+                }
+            }
+        }
+    }
+
+    /// The part of a channel's trace that is on screen.
+    fn visible_trace<'a>(&self, channel: &'a Channel) -> &'a [Sample] {
+        trace_between(&channel.trace, self.visible_window)
+    }
+
+    fn trigger_channel(&self) -> Option<&Channel> {
+        self.channels.get(self.trigger.source)
+    }
+
+    /// Draggable trigger level handle on the right edge of the screen,
+    /// plus a marker for the trigger position at the horizontal origin.
+    fn trigger_level_marker(
+        &mut self,
+        ui: &egui::Ui,
+        display: &mut DisplayList,
+        grid: &ScreenGrid,
+    ) {
+        let Some(channel) = self.channels.get(self.trigger.source) else {
+            return;
+        };
+        let color = channel.color;
+        let rect = grid.rect;
+        let y = grid
+            .y_for_divisions(channel.to_divisions(self.trigger.level))
+            .clamp(rect.top(), rect.bottom());
+
+        let handle_size = 10.0;
+        let handle_rect = egui::Rect::from_min_max(
+            egui::pos2(rect.right() - 2.0 * handle_size, y - handle_size),
+            egui::pos2(rect.right(), y + handle_size),
+        );
+        let response = ui
+            .interact(
+                handle_rect,
+                ui.id().with("trigger_level"),
+                egui::Sense::drag(),
+            )
+            .on_hover_cursor(egui::CursorIcon::ResizeVertical);
+        if response.dragged() {
+            let new_y = response.interact_pointer_pos().map_or(y, |p| p.y);
+            self.trigger.level = channel.volts_at_divisions(grid.divisions_at(new_y));
+        }
+
+        if response.hovered() || response.dragged() {
+            display.line_segment(
+                [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                egui::Stroke::new(1.0, color.gamma_multiply(0.6)),
+            );
+        }
+        display.add(egui::Shape::convex_polygon(
+            vec![
+                egui::pos2(rect.right(), y - handle_size / 2.0),
+                egui::pos2(rect.right(), y + handle_size / 2.0),
+                egui::pos2(rect.right() - handle_size, y),
+            ],
+            color,
+            egui::Stroke::NONE,
+        ));
+
+        // Trigger position: t = 0
+        let x = grid.origin.x;
+        display.add(egui::Shape::convex_polygon(
+            vec![
+                egui::pos2(x - handle_size / 2.0, rect.top()),
+                egui::pos2(x + handle_size / 2.0, rect.top()),
+                egui::pos2(x, rect.top() + handle_size),
+            ],
+            color,
+            egui::Stroke::NONE,
+        ));
+    }
+
+    /// Whether to acquire this frame. The records are reacquired as often as
+    /// [`EVALUATIONS_PER_SECOND`] allows, and right away when the settings change.
+    fn acquisition_due(&mut self) -> bool {
+        let settings = AcquisitionSettings {
+            acquisition: self.acquisition.clone(),
+            trigger: self.trigger,
+            scale_div_ms: self.scale_div_ms,
+            channels: self.channels.iter().map(ChannelSettings::of).collect(),
+        };
+        let records = self.channels.iter().filter(|c| c.math.is_none()).count();
+        let interval = (self.acquisition.evaluations() * records) as f64 / EVALUATIONS_PER_SECOND;
+        if self.acquired_with.as_ref() == Some(&settings)
+            && self.phase - self.acquired_at < interval
+        {
+            return false;
+        }
+        self.acquired_with = Some(settings);
+        self.acquired_at = self.phase;
+        true
+    }
+
+    /// Acquire `count` points of the `record` time window (relative to the trigger point)
+    /// into every channel's trace, following the trigger sweep mode.
+    ///
+    /// The trigger looks for its next event in one record length of signal from the
+    /// acquisition clock on, and every channel is then sampled around it. Returns false if
+    /// the traces were kept because there was no trigger event.
+    fn acquire(&mut self, record: (f64, f64), count: usize) -> bool {
+        let (t_start, t_end) = record;
+        let count = count.max(2);
+        let dt = (t_end - t_start) / (count - 1) as f64;
+        let clock = self.phase;
+        let mode = if self.single_shot {
+            SweepMode::Single
+        } else {
+            self.trigger.mode
+        };
+
+        let event = self
+            .channels
+            .get(self.trigger.source)
+            .filter(|channel| channel.math.is_none())
+            .and_then(|channel| {
+                find_trigger(
+                    self.trigger,
+                    self.last_trigger,
+                    channel,
+                    (clock, clock + t_end - t_start),
+                    dt,
+                )
+            });
+
+        let t0 = match event {
+            Some(t) => {
+                self.last_trigger = Some(t);
+                self.trigger_status = TriggerStatus::Triggered;
+                t
+            }
+            None if mode == SweepMode::Auto => {
+                self.trigger_status = TriggerStatus::Auto;
+                clock
+            }
+            None => {
+                self.trigger_status = TriggerStatus::Waiting;
+                return false;
+            }
+        };
+
+        let averages = self.acquisition.average_count();
+        for channel in &mut self.channels {
+            if channel.math.is_some() {
+                continue;
+            }
+            let raw = sample_channel(&self.acquisition, channel, t0 + t_start, dt, count);
+            let mut trace: Vec<Sample> = raw
+                .into_iter()
+                .map(|s| Sample {
+                    t: s.t - t0,
+                    v: s.v,
+                })
+                .collect();
+            if !trace.is_empty() {
+                channel.dc_level = trace.iter().map(|s| s.v).sum::<f64>() / trace.len() as f64;
+            }
+            channel.coupling.apply(&mut trace);
+            channel.trace = if self.acquisition.mode == AcquisitionMode::Average {
+                channel.average.add(trace, averages)
+            } else {
+                channel.average.clear();
+                trace
+            };
+        }
+        self.compute_math();
+
+        if mode == SweepMode::Single {
+            self.running = false;
+            self.single_shot = false;
+        }
+        true
+    }
+}
+
+/// Next event of the `trigger` on `channel` after the `previous` one and its holdoff, in
+/// the `window` (s).
+///
+/// The window is scanned in at most [`TRIGGER_SCAN_POINTS`] points, and the event is then
+/// placed between the two scan points around it with the record's sample spacing `dt`, so
+/// a deep record costs no more than a short one to trigger on.
+fn find_trigger(
+    mut trigger: Trigger,
+    previous: Option<f64>,
+    channel: &Channel,
+    window: (f64, f64),
+    dt: f64,
+) -> Option<f64> {
+    let (start, end) = window;
+    let points = (((end - start) / dt).round() as usize + 1).clamp(2, TRIGGER_SCAN_POINTS);
+    let scan = channel.signal().samples(start, end, points);
+    match channel.coupling {
+        Coupling::Dc => {}
+        // Level with the mean taken off, as the trace will be
+        Coupling::Ac => {
+            trigger.level += scan.iter().map(|s| s.v).sum::<f64>() / scan.len() as f64;
+        }
+        Coupling::Gnd => return None,
+    }
+    let after = previous.map_or(f64::NEG_INFINITY, |t| t + trigger.holdoff);
+
+    // A crossing in the scan may turn out to be no later than `after` at the finer spacing
+    let step = (end - start) / (points - 1) as f64;
+    let mut scanned_after = after;
+    loop {
+        let coarse = trigger.find(&scan, scanned_after)?;
+        let fine_start = (coarse - step).max(start);
+        let fine_end = (coarse + step).min(end);
+        let fine_points = ((fine_end - fine_start) / dt).ceil() as usize + 1;
+        let fine = channel
+            .signal()
+            .samples(fine_start, fine_end, fine_points.max(2));
+        if let Some(t) = trigger.find(&fine, after) {
+            return Some(t);
+        }
+        scanned_after = coarse;
+    }
+}
+
+/// A channel's record of `count` points `dt` apart from `start`, through its ADC if the
+/// acquisition has one.
+fn sample_channel(
+    acquisition: &Acquisition,
+    channel: &Channel,
+    start: f64,
+    dt: f64,
+    count: usize,
+) -> Vec<Sample> {
+    let adc = acquisition
+        .resolution
+        .map(|bits| channel_adc(bits, channel));
+    let end = start + count.saturating_sub(1) as f64 * dt;
+    acquisition.sample(channel.signal(), start, end, count, adc)
+}
+
+/// ADC of a channel, with the 8 divisions of the screen as its full scale.
+///
+/// AC coupling takes the DC level off before the ADC, so an AC-coupled channel's range is
+/// centred on the mean of its last record.
+fn channel_adc(bits: u32, channel: &Channel) -> Adc {
+    let half = f64::from(ScreenGrid::VDIVS) / 2.0;
+    let dc_level = if channel.coupling == Coupling::Ac {
+        channel.dc_level
+    } else {
+        0.0
+    };
+    Adc {
+        bits,
+        low: channel.volts_at_divisions(-half) + dc_level,
+        high: channel.volts_at_divisions(half) + dc_level,
+    }
+}
+
+/// The samples of a time-ordered `trace` from `t_start` to `t_end` (s).
+fn trace_between(trace: &[Sample], (t_start, t_end): (f64, f64)) -> &[Sample] {
+    let tolerance = (t_end - t_start).abs() * 1e-9;
+    let first = trace.partition_point(|s| s.t < t_start - tolerance);
+    let end = trace.partition_point(|s| s.t <= t_end + tolerance);
+    &trace[first..end.max(first)]
+}
+
+/// Side-panel section for one channel. Returns true if the user asked to remove it.
+///
+/// `clock` is the acquisition clock, which is also the play head of recordings.
+fn channel_panel(
+    ui: &mut egui::Ui,
+    index: usize,
+    channel: &mut Channel,
+    names: &[String],
+    clock: f64,
+) -> bool {
+    let mut remove = false;
+    let title = egui::RichText::new(&channel.name)
+        .color(channel.color)
+        .strong();
+    egui::CollapsingHeader::new(title)
+        .id_salt(("channel", index))
+        .default_open(index == 0)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut channel.enabled, "On");
+                ui.color_edit_button_srgba(&mut channel.color);
+                if ui.button("Remove").clicked() {
+                    remove = true;
+                }
+            });
+
+            if let Some(math) = &mut channel.math {
+                math_panel(ui, index, math, names);
+            } else if let Some(recording) = &mut channel.recording {
+                ui.label(format!("File: {}", recording.name));
+                ui.label(format!(
+                    "{} samples, {:.3} s at {:.1} S/s",
+                    recording.len(),
+                    recording.duration(),
+                    recording.sample_rate()
+                ));
+                if let Some(mut full_scale) = recording.full_scale() {
+                    ui.horizontal(|ui| {
+                        ui.label("Full scale:");
+                        let edit = egui::DragValue::new(&mut full_scale)
+                            .speed(0.01)
+                            .range(0.001..=1000.0)
+                            .suffix(" V");
+                        if ui.add(edit).changed() {
+                            // Shares the samples with the recording being replaced
+                            Arc::make_mut(recording).set_full_scale(full_scale);
                         }
+                    });
+                }
+                let play_head = clock.rem_euclid(recording.duration());
+                ui.add(
+                    egui::ProgressBar::new((play_head / recording.duration()) as f32)
+                        .text(format!("{play_head:.2} s")),
+                );
+                if ui.button("Unload").clicked() {
+                    channel.recording = None;
+                }
+            } else {
+                generator_panel(ui, index, &mut channel.source, channel.volts_per_div);
+            }
+
+            ui.add_space(4.0);
+
+            ui.label(format!("{}/div:", channel.unit()));
+            ui.add(egui::Slider::new(&mut channel.volts_per_div, 0.1..=200.0));
+            ui.label(format!("{:.2}", channel.volts_per_div));
+
+            ui.horizontal(|ui| {
+                ui.label("Vertical offset:");
+                ui.add(
+                    egui::DragValue::new(&mut channel.offset)
+                        .speed(0.01 * channel.volts_per_div)
+                        .suffix(" V"),
+                );
+            });
+
+            egui::ComboBox::from_id_salt(("coupling", index))
+                .selected_text(channel.coupling.name())
+                .show_ui(ui, |ui| {
+                    for coupling in [Coupling::Dc, Coupling::Ac, Coupling::Gnd] {
+                        ui.selectable_value(&mut channel.coupling, coupling, coupling.name());
                     }
-                    WaveformType::Triangle => {
-                        // Triangle wave using asin of sin to create triangle shape
-                        let triangle_phase = (2.0 * phase.sin()).clamp(-1.0, 1.0).asin();
-                        amplitude * (2.0 / std::f32::consts::PI) * triangle_phase
+                });
+        });
+    remove
+}
+
+/// Operator and operands of a math channel.
+fn math_panel(ui: &mut egui::Ui, index: usize, math: &mut Math, names: &[String]) {
+    egui::Grid::new(("math", index)).show(ui, |ui| {
+        ui.label("Function:");
+        egui::ComboBox::from_id_salt(("math_operator", index))
+            .selected_text(math.operator.name())
+            .show_ui(ui, |ui| {
+                for operator in Operator::ALL {
+                    ui.selectable_value(&mut math.operator, operator, operator.name());
+                }
+            });
+        ui.end_row();
+
+        let mut operands = vec![("A:", &mut math.a)];
+        if math.operator.is_binary() {
+            operands.push(("B:", &mut math.b));
+        }
+        for (label, operand) in operands {
+            ui.label(label);
+            egui::ComboBox::from_id_salt(("math_operand", index, label))
+                .selected_text(names.get(*operand).map_or("", String::as_str))
+                .show_ui(ui, |ui| {
+                    for (other, name) in names.iter().enumerate() {
+                        if other != index {
+                            ui.selectable_value(operand, other, name);
+                        }
                     }
-                };
-                let y = origin_y - (v / volts_per_div) * cell_size;
-                points.push(egui::pos2(x, y));
+                });
+            ui.end_row();
+        }
+
+        if math.operator == Operator::ScaleOffset {
+            ui.label("k:");
+            ui.add(egui::DragValue::new(&mut math.gain).speed(0.01));
+            ui.end_row();
+            ui.label("c:");
+            ui.add(
+                egui::DragValue::new(&mut math.offset)
+                    .speed(0.01)
+                    .suffix(" V"),
+            );
+            ui.end_row();
+        }
+    });
+}
+
+/// Controls for a function generator.
+fn generator_panel(ui: &mut egui::Ui, index: usize, generator: &mut Generator, volts_per_div: f64) {
+    ui.label("Waveform:");
+    egui::ComboBox::from_id_salt(("waveform_type", index))
+        .selected_text(generator.waveform_type.name())
+        .show_ui(ui, |ui| {
+            for waveform_type in WaveformType::ALL {
+                ui.selectable_value(
+                    &mut generator.waveform_type,
+                    waveform_type,
+                    waveform_type.name(),
+                );
             }
+        });
 
-            painter.add(egui::Shape::line(
-                points,
-                egui::Stroke::new(2.0, egui::Color32::YELLOW),
-            ));
+    let waveform_type = generator.waveform_type;
+    if waveform_type == WaveformType::Pulse {
+        ui.label("Duty cycle (%):");
+        let mut duty_percent = generator.duty * 100.0;
+        if ui
+            .add(egui::Slider::new(&mut duty_percent, 0.0..=100.0))
+            .changed()
+        {
+            generator.duty = duty_percent / 100.0;
+        }
+    }
+    if matches!(waveform_type, WaveformType::Square | WaveformType::Pulse) {
+        ui.horizontal(|ui| {
+            ui.label("Edge time:");
+            let mut edge_us = generator.edge_time * 1e6;
+            if ui
+                .add(
+                    egui::DragValue::new(&mut edge_us)
+                        .speed(1.0)
+                        .range(0.0..=100_000.0)
+                        .suffix(" µs"),
+                )
+                .changed()
+            {
+                generator.edge_time = edge_us / 1e6;
+            }
+        });
+    }
+    if waveform_type == WaveformType::Expression {
+        expression_editor(ui, &mut generator.expression);
+    }
+    if waveform_type.is_noise() {
+        ui.horizontal(|ui| {
+            ui.label("Seed:");
+            ui.add(egui::DragValue::new(&mut generator.seed));
+        });
+    } else if waveform_type != WaveformType::Dc {
+        ui.label("Frequency (Hz):");
+        ui.add(egui::Slider::new(&mut generator.freq, 0.1..=500.0).logarithmic(true));
+        ui.label(format!("{:.1}", generator.freq));
+    }
 
-            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                powered_by_egui_and_eframe(ui);
-                egui::warn_if_debug_build(ui);
-            });
+    ui.label(match waveform_type {
+        WaveformType::Dc => "Level (V):",
+        WaveformType::WhiteNoise | WaveformType::PinkNoise => "RMS (V):",
+        _ => "Amplitude (V):",
+    });
+    ui.add(egui::Slider::new(&mut generator.amplitude, 0.1..=200.0));
+    ui.label(format!("{:.2}", generator.amplitude));
+
+    ui.horizontal(|ui| {
+        ui.label("DC offset:");
+        ui.add(
+            egui::DragValue::new(&mut generator.offset_volts)
+                .speed(0.01 * volts_per_div)
+                .suffix(" V"),
+        );
+    });
+    if !waveform_type.is_noise() && waveform_type != WaveformType::Dc {
+        ui.horizontal(|ui| {
+            ui.label("Phase:");
+            ui.add(
+                egui::DragValue::new(&mut generator.phase_deg)
+                    .speed(1.0)
+                    .range(-360.0..=360.0)
+                    .suffix("°"),
+            );
         });
     }
 }
 
+/// Single-line formula editor with inline parse errors.
+fn expression_editor(ui: &mut egui::Ui, expression: &mut Expression) {
+    ui.label("Formula:");
+    let mut text = expression.text().to_owned();
+    if ui
+        .add(egui::TextEdit::singleline(&mut text).code_editor())
+        .on_hover_text("Variables: t, f, A. Constants: pi, e.\nOperators: + - * / ^ and functions like sin, exp, sqrt, min, max.")
+        .changed()
+    {
+        *expression = Expression::new(text);
+    }
+    if let Some(err) = expression.error() {
+        ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+    }
+}
+
+/// A measurement of a trace in `trace_unit`, with its unit, or a dash if it could not be made.
+fn format_measurement(
+    measurements: Option<&Measurements>,
+    measurement: Measurement,
+    trace_unit: &str,
+) -> String {
+    measurements.and_then(|m| m.get(measurement)).map_or_else(
+        || "—".to_owned(),
+        |value| measurement.format(value, trace_unit),
+    )
+}
+
+/// Channel label on the left edge, at the channel's 0 V level.
+fn ground_marker(display: &mut DisplayList, grid: &ScreenGrid, channel: &Channel) {
+    let rect = grid.rect;
+    let y = grid
+        .y_for_divisions(channel.to_divisions(0.0))
+        .clamp(rect.top(), rect.bottom());
+    let size = 10.0;
+    display.add(egui::Shape::convex_polygon(
+        vec![
+            egui::pos2(rect.left(), y - size / 2.0),
+            egui::pos2(rect.left() + size, y),
+            egui::pos2(rect.left(), y + size / 2.0),
+        ],
+        channel.color,
+        egui::Stroke::NONE,
+    ));
+    display.text(
+        egui::pos2(rect.left() + size + 2.0, y),
+        egui::Align2::LEFT_CENTER,
+        &channel.name,
+        egui::FontId::monospace(10.0),
+        channel.color,
+    );
+}
+
+/// Where the grid sits on screen this frame.
+///
+/// Grid coordinates are in divisions, with (0,0) at `origin` and +y pointing up.
+struct ScreenGrid {
+    rect: egui::Rect,
+    origin: egui::Pos2,
+
+    /// Size of one (square) division in points.
+    cell_size: f32,
+}
+
+impl ScreenGrid {
+    // Fixed grid: 10 horizontal, 8 vertical divisions
+    const HDIVS: f32 = 10.0;
+    const VDIVS: f32 = 8.0;
+
+    fn new(rect: egui::Rect, zoom: f32, pan: egui::Vec2) -> Self {
+        // Always use a square cell size, scaled by zoom
+        let cell_size = (rect.width() / Self::HDIVS).min(rect.height() / Self::VDIVS) * zoom;
+
+        // Define the screen position of (0,0): center of panel plus pan offset
+        let origin = rect.center() + pan;
+
+        Self {
+            rect,
+            origin,
+            cell_size,
+        }
+    }
+
+    /// Time (seconds) at screen position `x`.
+    fn time_at(&self, x: f32, secs_per_div: f64) -> f64 {
+        f64::from((x - self.origin.x) / self.cell_size) * secs_per_div
+    }
+
+    /// Screen x of the time `t` (seconds).
+    fn x_for_time(&self, t: f64, secs_per_div: f64) -> f32 {
+        self.origin.x + (t / secs_per_div) as f32 * self.cell_size
+    }
+
+    /// Screen x of a point `divisions` right of the center line.
+    fn x_for_divisions(&self, divisions: f64) -> f32 {
+        self.origin.x + divisions as f32 * self.cell_size
+    }
+
+    /// Screen y of a point `divisions` above the center line.
+    fn y_for_divisions(&self, divisions: f64) -> f32 {
+        self.origin.y - divisions as f32 * self.cell_size
+    }
+
+    /// Divisions above the center line at screen position `y`.
+    fn divisions_at(&self, y: f32) -> f64 {
+        f64::from((self.origin.y - y) / self.cell_size)
+    }
+}
+
+/// Screen positions of the on-screen part of a channel's trace.
+///
+/// With more than two samples per pixel, each pixel column is reduced to its highest and
+/// lowest point, so the trace keeps its peaks. Otherwise every sample is kept, and with
+/// [`Interpolation::Sinc`] samples at least two pixels apart are filled in with one
+/// reconstructed point per pixel.
+fn screen_points(
+    channel: &Channel,
+    grid: &ScreenGrid,
+    secs_per_div: f64,
+    interpolation: Interpolation,
+) -> Vec<egui::Pos2> {
+    let trace = &channel.trace;
+    let t_left = grid.time_at(grid.rect.left(), secs_per_div);
+    let t_right = grid.time_at(grid.rect.right(), secs_per_div);
+    // One sample past each edge, so the line reaches it
+    let first = trace.partition_point(|s| s.t < t_left).saturating_sub(1);
+    let end = (trace.partition_point(|s| s.t <= t_right) + 1).min(trace.len());
+    let visible = trace.get(first..end).unwrap_or_default();
+    let to_pos = |s: &Sample| {
+        egui::pos2(
+            grid.x_for_time(s.t, secs_per_div),
+            grid.y_for_divisions(channel.to_divisions(s.v)),
+        )
+    };
+    if interpolation == Interpolation::Sinc
+        && visible.len() >= 2
+        && (visible.len() as f32) < grid.rect.width() / 2.0
+    {
+        let (Some(first), Some(last)) = (visible.first(), visible.last()) else {
+            return Vec::new();
+        };
+        let left = to_pos(first).x.max(grid.rect.left() - 1.0);
+        let right = to_pos(last).x.min(grid.rect.right() + 1.0);
+        let count = (right - left).ceil().max(1.0) as usize + 1;
+        let t_left = grid.time_at(left, secs_per_div);
+        let t_right = grid.time_at(right, secs_per_div);
+        // From the whole trace, so points near the screen edges have samples on both sides
+        return sinc(trace, t_left, t_right, count)
+            .iter()
+            .map(to_pos)
+            .collect();
+    }
+    if visible.len() as f32 <= 2.0 * grid.rect.width() {
+        return visible.iter().map(to_pos).collect();
+    }
+
+    let mut points = Vec::with_capacity(2 * grid.rect.width() as usize + 4);
+    let mut flush = |top: egui::Pos2, bottom: egui::Pos2| {
+        let (a, b) = if top.x <= bottom.x {
+            (top, bottom)
+        } else {
+            (bottom, top)
+        };
+        points.push(a);
+        if b != a {
+            points.push(b);
+        }
+    };
+    // Pixel column, and its highest and lowest point
+    let mut column: Option<(f32, egui::Pos2, egui::Pos2)> = None;
+    for p in visible.iter().map(to_pos) {
+        match &mut column {
+            Some((x, top, bottom)) if *x == p.x.floor() => {
+                if p.y < top.y {
+                    *top = p;
+                }
+                if p.y > bottom.y {
+                    *bottom = p;
+                }
+            }
+            _ => {
+                if let Some((_, top, bottom)) = column {
+                    flush(top, bottom);
+                }
+                column = Some((p.x.floor(), p, p));
+            }
+        }
+    }
+    if let Some((_, top, bottom)) = column {
+        flush(top, bottom);
+    }
+    points
+}
+
+/// Splits `rect` into `count` rows of equal height, top to bottom.
+fn stack(rect: egui::Rect, count: usize) -> impl Iterator<Item = egui::Rect> {
+    let height = rect.height() / count.max(1) as f32;
+    (0..count).map(move |i| {
+        let top = rect.top() + i as f32 * height;
+        egui::Rect::from_x_y_ranges(rect.x_range(), top..=top + height)
+    })
+}
+
+fn draw_grid(display: &mut DisplayList, grid: &ScreenGrid) {
+    let rect = grid.rect;
+    let origin = grid.origin;
+    let cell_size = grid.cell_size;
+
+    // Draw square grid
+    let grid_color = egui::Color32::from_gray(60);
+    let strong_grid_color = egui::Color32::from_gray(90);
+    let stroke = egui::Stroke::new(1.0, grid_color);
+    let strong_stroke = egui::Stroke::new(1.5, strong_grid_color);
+
+    // Infinite grid: draw enough lines to fill the visible area, based on pan and zoom
+    // Compute the visible range in grid coordinates, centered at (0,0) = origin
+    let min_x = ((rect.left() - origin.x) / cell_size).floor() as isize - 2;
+    let max_x = ((rect.right() - origin.x) / cell_size).ceil() as isize + 2;
+    let min_y = ((rect.top() - origin.y) / cell_size).floor() as isize - 2;
+    let max_y = ((rect.bottom() - origin.y) / cell_size).ceil() as isize + 2;
+
+    // Vertical grid lines (x = 0 is the y-axis)
+    for i in min_x..=max_x {
+        let x = origin.x + (i as f32) * cell_size;
+        let s = if i == 0 { &strong_stroke } else { &stroke };
+        display.line_segment(
+            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+            *s,
+        );
+    }
+    // Horizontal grid lines (y = 0 is the x-axis)
+    for j in min_y..=max_y {
+        let y = origin.y + (j as f32) * cell_size;
+        let s = if j == 0 { &strong_stroke } else { &stroke };
+        display.line_segment(
+            [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+            *s,
+        );
+    }
+
+    // Minor increment ticks along both axes
+    let minor_ticks = 10;
+    let minor_tick_len = cell_size * 0.10;
+    let major_tick_len = cell_size * 0.22;
+    let tick_color = egui::Color32::from_gray(140);
+    for div in min_y..=max_y {
+        let div_top = origin.y + (div as f32) * cell_size;
+        // Major tick at the division, but skip if at axis (0,0) to avoid double-drawing
+        if div != 0 {
+            display.line_segment(
+                [
+                    egui::pos2(origin.x - major_tick_len / 2.0, div_top),
+                    egui::pos2(origin.x + major_tick_len / 2.0, div_top),
+                ],
+                egui::Stroke::new(1.5, tick_color),
+            );
+        }
+        // Minor ticks between divisions
+        for m in 1..minor_ticks {
+            let frac = m as f32 / minor_ticks as f32;
+            let y_tick = div_top + frac * cell_size;
+            display.line_segment(
+                [
+                    egui::pos2(origin.x - minor_tick_len / 2.0, y_tick),
+                    egui::pos2(origin.x + minor_tick_len / 2.0, y_tick),
+                ],
+                egui::Stroke::new(1.0, tick_color),
+            );
+        }
+    }
+    for div in min_x..=max_x {
+        let div_left = origin.x + (div as f32) * cell_size;
+        // Major tick at the division, but skip if at axis (0,0) to avoid double-drawing
+        if div != 0 {
+            display.line_segment(
+                [
+                    egui::pos2(div_left, origin.y - major_tick_len / 2.0),
+                    egui::pos2(div_left, origin.y + major_tick_len / 2.0),
+                ],
+                egui::Stroke::new(1.5, tick_color),
+            );
+        }
+        // Minor ticks between divisions
+        for m in 1..minor_ticks {
+            let frac = m as f32 / minor_ticks as f32;
+            let x_tick = div_left + frac * cell_size;
+            display.line_segment(
+                [
+                    egui::pos2(x_tick, origin.y - minor_tick_len / 2.0),
+                    egui::pos2(x_tick, origin.y + minor_tick_len / 2.0),
+                ],
+                egui::Stroke::new(1.0, tick_color),
+            );
+        }
+    }
+
+    // Small cross marking the origin
+    let tick_len = cell_size * 0.25;
+    let origin_stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+    display.line_segment(
+        [
+            egui::pos2(origin.x, origin.y - tick_len / 2.0),
+            egui::pos2(origin.x, origin.y + tick_len / 2.0),
+        ],
+        origin_stroke,
+    );
+    display.line_segment(
+        [
+            egui::pos2(origin.x - tick_len / 2.0, origin.y),
+            egui::pos2(origin.x + tick_len / 2.0, origin.y),
+        ],
+        origin_stroke,
+    );
+}
+
 fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
     ui.horizontal(|ui| {
         ui.spacing_mut().item_spacing.x = 0.0;
@@ -402,40 +1850,41 @@ fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
     });
 }
 
-// --- Oscilloscope waveform generation ---
-impl TemplateApp {
-    fn generate_waveform(&mut self) {
-        let n = self.waveform.len();
-        let hdivs = 10.0;
-        let time_per_div = self.scale_div_ms / 1000.0;
-        let full_time = hdivs * time_per_div;
-        let dt = full_time / (n as f32 - 1.0);
-        let freq = self.freq;
-        let amp = self.amplitude;
-
-        let center = n as isize / 2;
-        for i in 0..n {
-            let t = (i as isize - center) as f32 * dt;
-            let phase = 2.0 * std::f32::consts::PI * freq * t;
-            let v = match self.waveform_type {
-                WaveformType::Sine => amp * phase.sin(),
-                WaveformType::Square => {
-                    // Square wave: positive when in first half of period
-                    let period_pos = (phase / (2.0 * std::f32::consts::PI)).rem_euclid(1.0);
-                    if period_pos < 0.5 { amp } else { -amp }
-                }
-                WaveformType::Triangle => {
-                    // Triangle wave: sawtooth that goes up and down
-                    let period_pos = (phase / (2.0 * std::f32::consts::PI)).rem_euclid(1.0);
-                    let triangle_val = if period_pos < 0.5 {
-                        4.0 * period_pos - 1.0
-                    } else {
-                        3.0 - 4.0 * period_pos
-                    };
-                    amp * triangle_val
-                }
-            };
-            self.waveform[i] = v;
-        }
+#[cfg(test)]
+mod tests {
+    use super::find_trigger;
+    use crate::channel::{Channel, Coupling};
+    use crate::signal::WaveformType;
+    use crate::trigger::Trigger;
+
+    #[test]
+    fn trigger_is_placed_at_the_record_sample_spacing() {
+        let mut channel = Channel::new(0);
+        channel.source.waveform_type = WaveformType::Sine;
+        channel.source.freq = 1.0;
+        channel.source.amplitude = 1.0;
+        let trigger = Trigger {
+            level: 0.5,
+            ..Trigger::default()
+        };
+
+        // sin(2πt) = 0.5 rising at t = 1/12, within a 10 MS record of 1 s
+        let dt = 1.0 / 9_999_999.0;
+        let Some(t) = find_trigger(trigger, None, &channel, (0.0, 1.0), dt) else {
+            panic!("no event");
+        };
+        assert!((t - 1.0 / 12.0).abs() < dt, "event at {t}");
+
+        let Some(next) = find_trigger(trigger, Some(t), &channel, (0.0, 2.0), dt) else {
+            panic!("no second event");
+        };
+        assert!((next - 13.0 / 12.0).abs() < dt, "next event at {next}");
+
+        channel.coupling = Coupling::Gnd;
+        assert_eq!(
+            find_trigger(trigger, None, &channel, (0.0, 1.0), dt),
+            None,
+            "grounded input"
+        );
     }
 }
@@ -0,0 +1,435 @@
+//! Saving the scope display as PNG or SVG.
+//!
+//! The scope is drawn into a [`DisplayList`] rather than straight onto a painter, so the
+//! exact shapes shown on screen can also be written out as vector graphics.
+
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use egui::epaint::{ClippedShape, ColorMode, Galley, PathShape, Shape, TextShape};
+use egui::{Color32, ColorImage, Rect, TextureId};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ImageFormat {
+    Png,
+    Svg,
+}
+
+/// Shapes drawn this frame, each clipped to the painter's clip rect when it was added.
+pub(super) struct DisplayList {
+    painter: egui::Painter,
+    shapes: Vec<ClippedShape>,
+
+    /// Pixels of the textures drawn with [`Self::image`], for embedding in SVG.
+    images: Vec<(TextureId, Arc<ColorImage>)>,
+}
+
+impl DisplayList {
+    pub fn new(painter: egui::Painter) -> Self {
+        Self {
+            painter,
+            shapes: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    /// Clip the shapes added from now on to `rect`.
+    pub fn set_clip_rect(&mut self, rect: Rect) {
+        self.painter.set_clip_rect(rect);
+    }
+
+    pub fn add(&mut self, shape: Shape) {
+        self.shapes.push(ClippedShape {
+            clip_rect: self.painter.clip_rect(),
+            shape,
+        });
+    }
+
+    pub fn line_segment(&mut self, points: [egui::Pos2; 2], stroke: impl Into<egui::Stroke>) {
+        self.add(Shape::line_segment(points, stroke));
+    }
+
+    /// Draws `texture`, whose pixels are `image`, stretched over `rect`.
+    pub fn image(&mut self, texture: &egui::TextureHandle, rect: Rect, image: Arc<ColorImage>) {
+        let uv = Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        self.add(Shape::image(texture.id(), rect, uv, Color32::WHITE));
+        self.images.push((texture.id(), image));
+    }
+
+    /// Lays out a single line of text, e.g. to measure it before calling [`Self::galley`].
+    pub fn layout(&self, text: String, font_id: egui::FontId, color: Color32) -> Arc<Galley> {
+        self.painter.layout_no_wrap(text, font_id, color)
+    }
+
+    pub fn galley(&mut self, pos: egui::Pos2, galley: Arc<Galley>) {
+        self.add(Shape::galley(pos, galley, Color32::PLACEHOLDER));
+    }
+
+    /// Like [`egui::Painter::text`].
+    pub fn text(
+        &mut self,
+        pos: egui::Pos2,
+        anchor: egui::Align2,
+        text: impl Into<String>,
+        font_id: egui::FontId,
+        color: Color32,
+    ) -> Rect {
+        let galley = self.layout(text.into(), font_id, color);
+        let rect = anchor.anchor_size(pos, galley.size());
+        self.galley(rect.min, galley);
+        rect
+    }
+
+    /// Hands the shapes to the painter, to be drawn on screen.
+    pub fn paint(&self) {
+        for clipped in &self.shapes {
+            self.painter
+                .with_clip_rect(clipped.clip_rect)
+                .add(clipped.shape.clone());
+        }
+    }
+
+    /// An SVG document of the area `rect`, filled with `background` behind the shapes.
+    pub fn to_svg(&self, rect: Rect, background: Color32) -> String {
+        let mut svg = String::new();
+        // Writing to a `String` never fails
+        write_svg(&mut svg, rect, background, self).unwrap_or_default();
+        svg
+    }
+}
+
+fn write_svg(
+    svg: &mut String,
+    rect: Rect,
+    background: Color32,
+    display: &DisplayList,
+) -> std::fmt::Result {
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">"#,
+        x = rect.left(),
+        y = rect.top(),
+        w = rect.width(),
+        h = rect.height(),
+    )?;
+    writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+        rect.left(),
+        rect.top(),
+        rect.width(),
+        rect.height(),
+        fill(background)
+    )?;
+
+    // One group per run of shapes with the same clip rect
+    let mut clip_rects: Vec<Rect> = Vec::new();
+    let mut current_clip = None;
+    for clipped in &display.shapes {
+        let r = clipped.clip_rect;
+        if current_clip != Some(r) {
+            if current_clip.is_some() {
+                svg.push_str("</g>\n");
+            }
+            let id = if let Some(id) = clip_rects.iter().position(|c| *c == r) {
+                id
+            } else {
+                clip_rects.push(r);
+                writeln!(
+                    svg,
+                    r#"<clipPath id="clip{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                    clip_rects.len() - 1,
+                    r.left(),
+                    r.top(),
+                    r.width(),
+                    r.height()
+                )?;
+                clip_rects.len() - 1
+            };
+            writeln!(svg, r#"<g clip-path="url(#clip{id})">"#)?;
+            current_clip = Some(r);
+        }
+        write_shape(svg, &clipped.shape, &display.images)?;
+    }
+    if current_clip.is_some() {
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    Ok(())
+}
+
+fn write_shape(
+    svg: &mut String,
+    shape: &Shape,
+    images: &[(TextureId, Arc<ColorImage>)],
+) -> std::fmt::Result {
+    match shape {
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                write_shape(svg, shape, images)?;
+            }
+        }
+        Shape::LineSegment { points, stroke } => writeln!(
+            svg,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}"{}/>"#,
+            points[0].x,
+            points[0].y,
+            points[1].x,
+            points[1].y,
+            self::stroke(stroke.width, stroke.color)
+        )?,
+        Shape::Path(path) => write_path(svg, path)?,
+        Shape::Rect(shape) => {
+            let r = shape.rect;
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}"{}{}/>"#,
+                r.left(),
+                r.top(),
+                r.width(),
+                r.height(),
+                shape.corner_radius.average(),
+                fill(shape.fill),
+                stroke(shape.stroke.width, shape.stroke.color)
+            )?;
+        }
+        Shape::Circle(circle) => writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}"{}{}/>"#,
+            circle.center.x,
+            circle.center.y,
+            circle.radius,
+            fill(circle.fill),
+            stroke(circle.stroke.width, circle.stroke.color)
+        )?,
+        Shape::Text(text) => write_text(svg, text)?,
+        Shape::Mesh(mesh) => {
+            let image = images.iter().find(|(id, _)| *id == mesh.texture_id);
+            if let Some(png) = image.and_then(|(_, image)| png_bytes(image).ok()) {
+                let bounds = mesh.calc_bounds();
+                writeln!(
+                    svg,
+                    r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+                    bounds.left(),
+                    bounds.top(),
+                    bounds.width(),
+                    bounds.height(),
+                    base64(&png)
+                )?;
+            }
+        }
+        // Nothing else is drawn on the scope
+        _ => {}
+    }
+    Ok(())
+}
+
+fn write_path(svg: &mut String, path: &PathShape) -> std::fmt::Result {
+    let points: Vec<String> = path
+        .points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect();
+    let color = match &path.stroke.color {
+        ColorMode::Solid(color) => *color,
+        ColorMode::UV(_) => Color32::GRAY,
+    };
+    let element = if path.closed { "polygon" } else { "polyline" };
+    writeln!(
+        svg,
+        r#"<{element} points="{}"{}{} stroke-linejoin="round"/>"#,
+        points.join(" "),
+        fill(path.fill),
+        stroke(path.stroke.width, color)
+    )
+}
+
+fn write_text(svg: &mut String, text: &TextShape) -> std::fmt::Result {
+    let Some(format) = text.galley.job.sections.first().map(|s| &s.format) else {
+        return Ok(());
+    };
+    let mut color = text.override_text_color.unwrap_or(format.color);
+    if color == Color32::PLACEHOLDER {
+        color = text.fallback_color;
+    }
+    let family = match format.font_id.family {
+        egui::FontFamily::Monospace => "monospace",
+        egui::FontFamily::Proportional | egui::FontFamily::Name(_) => "sans-serif",
+    };
+    for row in &text.galley.rows {
+        let pos = text.pos + row.pos.to_vec2();
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="{family}" font-size="{}" dominant-baseline="text-before-edge"{}>{}</text>"#,
+            pos.x,
+            pos.y,
+            format.font_id.size,
+            fill(color),
+            escape(&row.text())
+        )?;
+    }
+    Ok(())
+}
+
+/// SVG fill attributes.
+fn fill(color: Color32) -> String {
+    if color.a() == 0 {
+        return r#" fill="none""#.to_owned();
+    }
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    if a < 255 {
+        format!(
+            r##" fill="#{r:02x}{g:02x}{b:02x}" fill-opacity="{:.3}""##,
+            f32::from(a) / 255.0
+        )
+    } else {
+        format!(r##" fill="#{r:02x}{g:02x}{b:02x}""##)
+    }
+}
+
+/// SVG stroke attributes.
+fn stroke(width: f32, color: Color32) -> String {
+    if width <= 0.0 || color.a() == 0 {
+        return String::new();
+    }
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    if a < 255 {
+        format!(
+            r##" stroke="#{r:02x}{g:02x}{b:02x}" stroke-width="{width}" stroke-opacity="{:.3}""##,
+            f32::from(a) / 255.0
+        )
+    } else {
+        format!(r##" stroke="#{r:02x}{g:02x}{b:02x}" stroke-width="{width}""##)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Encodes the part of a screenshot covered by `rect` (in points) as PNG.
+pub(super) fn encode_png(
+    screenshot: &egui::ColorImage,
+    rect: Rect,
+    pixels_per_point: f32,
+) -> Result<Vec<u8>, png::EncodingError> {
+    png_bytes(&screenshot.region(&rect, Some(pixels_per_point)))
+}
+
+fn png_bytes(image: &ColorImage) -> Result<Vec<u8>, png::EncodingError> {
+    let [width, height] = image.size;
+    let rgba: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_srgba_unmultiplied())
+        .collect();
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&rgba)?;
+    Ok(png)
+}
+
+/// Standard base64 with padding, for data URLs.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let bits = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, write_shape};
+    use egui::epaint::{PathShape, Shape};
+    use egui::{Color32, Stroke, pos2};
+
+    fn svg(shape: &Shape) -> String {
+        let mut svg = String::new();
+        assert!(
+            write_shape(&mut svg, shape, &[]).is_ok(),
+            "writing to a string failed"
+        );
+        svg
+    }
+
+    #[test]
+    fn base64_test_vectors() {
+        // RFC 4648, section 10
+        for (input, expected) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(input.as_bytes()), expected, "{input:?}");
+        }
+        assert_eq!(
+            base64(&[0xfb, 0xff]),
+            "+/8=",
+            "last two letters of the alphabet"
+        );
+    }
+
+    #[test]
+    fn svg_line_and_path() {
+        let line = Shape::line_segment(
+            [pos2(1.0, 2.0), pos2(3.5, 4.0)],
+            Stroke::new(2.0, Color32::from_rgb(255, 0, 16)),
+        );
+        assert_eq!(
+            svg(&line),
+            "<line x1=\"1\" y1=\"2\" x2=\"3.5\" y2=\"4\" stroke=\"#ff0010\" stroke-width=\"2\"/>\n"
+        );
+
+        let path = Shape::Path(PathShape::line(
+            vec![pos2(0.0, 0.0), pos2(10.0, 5.0), pos2(20.0, 0.0)],
+            Stroke::new(1.5, Color32::from_rgba_unmultiplied(0, 255, 255, 51)),
+        ));
+        assert_eq!(
+            svg(&path),
+            "<polyline points=\"0,0 10,5 20,0\" fill=\"none\" stroke=\"#00ffff\" \
+             stroke-width=\"1.5\" stroke-opacity=\"0.200\" stroke-linejoin=\"round\"/>\n"
+        );
+    }
+
+    #[test]
+    fn svg_text() {
+        let ctx = egui::Context::default();
+        let _output = ctx.run(egui::RawInput::default(), |_| {});
+        let galley = ctx.fonts(|fonts| {
+            fonts.layout_no_wrap(
+                "a<b & c".to_owned(),
+                egui::FontId::monospace(12.0),
+                Color32::WHITE,
+            )
+        });
+        let text = Shape::galley(pos2(10.0, 20.0), galley, Color32::WHITE);
+        assert_eq!(
+            svg(&text),
+            "<text x=\"10\" y=\"20\" font-family=\"monospace\" font-size=\"12\" \
+             dominant-baseline=\"text-before-edge\" fill=\"#ffffff\">a&lt;b &amp; c</text>\n"
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Draggable time and voltage cursors.
+
+use super::ScreenGrid;
+use super::capture::DisplayList;
+use crate::channel::Channel;
+use crate::measure::format_si;
+use crate::signal::Sample;
+
+/// Two time cursors and two voltage cursors.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct Cursors {
+    pub show_time: bool,
+    pub show_voltage: bool,
+
+    /// Times (s) of the time cursors, relative to the trigger point.
+    pub times: [f64; 2],
+
+    /// Voltages (V) of the voltage cursors, on the `source` channel's scale.
+    pub volts: [f64; 2],
+
+    /// Index of the channel the voltage cursors measure.
+    pub source: usize,
+
+    /// Put the voltage cursors where the `source` trace is at the time cursors.
+    pub track: bool,
+}
+
+impl Default for Cursors {
+    fn default() -> Self {
+        Self {
+            show_time: false,
+            show_voltage: false,
+            times: [-1e-3, 1e-3],
+            volts: [-1.0, 1.0],
+            source: 0,
+            track: false,
+        }
+    }
+}
+
+impl Cursors {
+    /// Side-panel controls.
+    pub fn panel(&mut self, ui: &mut egui::Ui, channels: &[Channel]) {
+        egui::CollapsingHeader::new("Cursors").show(ui, |ui| {
+            ui.checkbox(&mut self.show_time, "Time cursors");
+            ui.checkbox(&mut self.show_voltage, "Voltage cursors");
+            ui.add_enabled_ui(self.show_voltage, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Source:");
+                    egui::ComboBox::from_id_salt("cursor_source")
+                        .selected_text(
+                            channels
+                                .get(self.source)
+                                .map_or("", |channel| channel.name.as_str()),
+                        )
+                        .show_ui(ui, |ui| {
+                            for (index, channel) in channels.iter().enumerate() {
+                                ui.selectable_value(&mut self.source, index, &channel.name);
+                            }
+                        });
+                });
+                ui.add_enabled(
+                    self.show_time,
+                    egui::Checkbox::new(&mut self.track, "Track waveform"),
+                )
+                .on_hover_text("Snap the voltage cursors to the trace at the time cursors");
+            });
+        });
+    }
+
+    /// Drags, draws and labels the cursors on the scope, with a readout in the top left.
+    pub fn show(
+        &mut self,
+        ui: &egui::Ui,
+        display: &mut DisplayList,
+        grid: &ScreenGrid,
+        secs_per_div: f64,
+        channels: &[Channel],
+    ) {
+        let source = channels.get(self.source);
+        let tracking = self.track && self.show_time && self.show_voltage;
+        if tracking {
+            if let Some(channel) = source {
+                for (volts, &t) in self.volts.iter_mut().zip(&self.times) {
+                    if let Some(v) = value_at(&channel.trace, t) {
+                        *volts = v;
+                    }
+                }
+            }
+        }
+
+        let rect = grid.rect;
+        let font = egui::FontId::monospace(11.0);
+        let time_color = egui::Color32::from_gray(220);
+        if self.show_time {
+            for (index, t) in self.times.iter_mut().enumerate() {
+                let x = grid.x_for_time(*t, secs_per_div);
+                let handle = egui::Rect::from_x_y_ranges(x - 4.0..=x + 4.0, rect.y_range());
+                let response = ui
+                    .interact(
+                        handle,
+                        ui.id().with(("time_cursor", index)),
+                        egui::Sense::drag(),
+                    )
+                    .on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+                if let Some(pos) = response
+                    .interact_pointer_pos()
+                    .filter(|_| response.dragged())
+                {
+                    *t = grid.time_at(pos.x, secs_per_div);
+                }
+                let x = grid.x_for_time(*t, secs_per_div);
+                display.add(egui::Shape::Vec(egui::Shape::dashed_line(
+                    &[egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                    egui::Stroke::new(1.0, time_color),
+                    6.0,
+                    4.0,
+                )));
+                display.text(
+                    egui::pos2(x + 3.0, rect.top() + 14.0),
+                    egui::Align2::LEFT_TOP,
+                    format!("T{}", index + 1),
+                    font.clone(),
+                    time_color,
+                );
+            }
+        }
+
+        if let Some(channel) = source.filter(|_| self.show_voltage) {
+            for (index, v) in self.volts.iter_mut().enumerate() {
+                let y = grid.y_for_divisions(channel.to_divisions(*v));
+                if !tracking {
+                    let handle = egui::Rect::from_x_y_ranges(rect.x_range(), y - 4.0..=y + 4.0);
+                    let response = ui
+                        .interact(
+                            handle,
+                            ui.id().with(("voltage_cursor", index)),
+                            egui::Sense::drag(),
+                        )
+                        .on_hover_cursor(egui::CursorIcon::ResizeVertical);
+                    if let Some(pos) = response
+                        .interact_pointer_pos()
+                        .filter(|_| response.dragged())
+                    {
+                        *v = channel.volts_at_divisions(grid.divisions_at(pos.y));
+                    }
+                }
+                let y = grid.y_for_divisions(channel.to_divisions(*v));
+                display.add(egui::Shape::Vec(egui::Shape::dashed_line(
+                    &[egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                    egui::Stroke::new(1.0, channel.color),
+                    6.0,
+                    4.0,
+                )));
+                display.text(
+                    egui::pos2(rect.right() - 24.0, y - 2.0),
+                    egui::Align2::RIGHT_BOTTOM,
+                    format!("V{}", index + 1),
+                    font.clone(),
+                    channel.color,
+                );
+            }
+        }
+
+        let mut pos = rect.left_top() + egui::vec2(24.0, 24.0);
+        for (text, color) in self.readout(source) {
+            pos.y += display
+                .text(pos, egui::Align2::LEFT_TOP, text, font.clone(), color)
+                .height();
+        }
+    }
+
+    /// Readout lines and their colours.
+    fn readout(&self, source: Option<&Channel>) -> Vec<(String, egui::Color32)> {
+        let mut lines = Vec::new();
+        if self.show_time {
+            let [t1, t2] = self.times;
+            let dt = t2 - t1;
+            let frequency = if dt == 0.0 {
+                "—".to_owned()
+            } else {
+                format_si(1.0 / dt.abs(), "Hz")
+            };
+            lines.push((
+                format!(
+                    "T1 {}  T2 {}  Δt {}  1/Δt {frequency}",
+                    format_si(t1, "s"),
+                    format_si(t2, "s"),
+                    format_si(dt, "s")
+                ),
+                egui::Color32::from_gray(220),
+            ));
+        }
+        if let Some(channel) = source.filter(|_| self.show_voltage) {
+            let [v1, v2] = self.volts;
+            let unit = channel.unit();
+            lines.push((
+                format!(
+                    "{}: V1 {}  V2 {}  ΔV {}",
+                    channel.name,
+                    format_si(v1, unit),
+                    format_si(v2, unit),
+                    format_si(v2 - v1, unit)
+                ),
+                channel.color,
+            ));
+        }
+        lines
+    }
+}
+
+/// The trace's voltage at time `t`, linearly interpolated. `None` outside the trace.
+fn value_at(trace: &[Sample], t: f64) -> Option<f64> {
+    let index = trace.partition_point(|s| s.t < t);
+    let b = trace.get(index)?;
+    if index == 0 {
+        return (b.t == t).then_some(b.v);
+    }
+    let a = trace[index - 1];
+    Some(a.v + (t - a.t) / (b.t - a.t) * (b.v - a.v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cursors, value_at};
+    use crate::channel::Channel;
+    use crate::math::{Math, Operator};
+    use crate::signal::Sample;
+
+    #[test]
+    fn value_at_interpolates_within_the_trace() {
+        let trace = [
+            Sample { t: 0.0, v: 1.0 },
+            Sample { t: 1.0, v: 3.0 },
+            Sample { t: 2.0, v: -1.0 },
+        ];
+        assert_eq!(value_at(&trace, 0.0), Some(1.0), "first sample");
+        assert_eq!(value_at(&trace, 0.25), Some(1.5), "between samples");
+        assert_eq!(value_at(&trace, 1.5), Some(1.0), "falling");
+        assert_eq!(value_at(&trace, 2.0), Some(-1.0), "last sample");
+        assert_eq!(value_at(&trace, -0.1), None, "before the trace");
+        assert_eq!(value_at(&trace, 2.1), None, "after the trace");
+        assert_eq!(value_at(&[], 0.0), None, "empty trace");
+    }
+
+    #[test]
+    fn readout_shows_differences_in_the_source_unit() {
+        let mut cursors = Cursors {
+            show_time: true,
+            show_voltage: true,
+            times: [1e-3, 3e-3],
+            volts: [-0.5, 0.25],
+            ..Cursors::default()
+        };
+        let channel = Channel::new(0);
+        let lines = cursors.readout(Some(&channel));
+        assert_eq!(
+            lines[0].0, "T1 1.000 ms  T2 3.000 ms  Δt 2.000 ms  1/Δt 500.0 Hz",
+            "time cursors"
+        );
+        assert_eq!(
+            lines[1].0, "CH1: V1 -500.0 mV  V2 250.0 mV  ΔV 750.0 mV",
+            "voltage cursors"
+        );
+
+        let product = Channel::new_math(
+            0,
+            Math {
+                operator: Operator::Multiply,
+                ..Math::default()
+            },
+        );
+        cursors.times = [2e-3, 2e-3];
+        let lines = cursors.readout(Some(&product));
+        assert!(lines[0].0.ends_with("Δt 0.000 s  1/Δt —"), "{}", lines[0].0);
+        assert!(lines[1].0.ends_with("ΔV 750.0 mV²"), "{}", lines[1].0);
+    }
+}
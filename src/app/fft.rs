@@ -0,0 +1,344 @@
+//! Spectrum view, drawn under the time-domain view.
+
+use super::capture::DisplayList;
+use crate::channel::Channel;
+use crate::measure::format_si;
+use crate::spectrum::{Scale, Spectrum, Window, thd};
+
+/// Divisions of the spectrum grid.
+const HDIVS: usize = 10;
+const VDIVS: usize = 8;
+
+/// Longer records are cut to this many samples, to keep the FFT quick.
+const MAX_SAMPLES: usize = 1 << 20;
+
+/// Number of peaks marked on the spectrum.
+const PEAK_MARKERS: usize = 5;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct Fft {
+    pub enabled: bool,
+
+    /// Show the spectrum in place of the time view instead of under it.
+    pub spectrum_only: bool,
+
+    /// Index of the channel to analyse.
+    pub source: usize,
+
+    pub window: Window,
+    pub scale: Scale,
+
+    /// dBV at the top of the grid.
+    pub top_dbv: f64,
+
+    pub db_per_div: f64,
+
+    /// Number of harmonics in the readout, counting the fundamental.
+    pub harmonics: usize,
+
+    /// The spectrum of the last acquisition, and the source and window it was computed with.
+    #[serde(skip)]
+    spectrum: Option<(usize, Window, Spectrum)>,
+}
+
+impl Default for Fft {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spectrum_only: false,
+            source: 0,
+            window: Window::Hann,
+            scale: Scale::DbV,
+            top_dbv: 20.0,
+            db_per_div: 20.0,
+            harmonics: 5,
+            spectrum: None,
+        }
+    }
+}
+
+impl Fft {
+    /// Side-panel controls.
+    pub fn panel(&mut self, ui: &mut egui::Ui, channels: &[Channel]) {
+        egui::CollapsingHeader::new("FFT").show(ui, |ui| {
+            ui.checkbox(&mut self.enabled, "Show spectrum");
+            ui.add_enabled(
+                self.enabled,
+                egui::Checkbox::new(&mut self.spectrum_only, "Hide time view"),
+            );
+            egui::Grid::new("fft_settings").show(ui, |ui| {
+                ui.label("Source:");
+                egui::ComboBox::from_id_salt("fft_source")
+                    .selected_text(
+                        channels
+                            .get(self.source)
+                            .map_or("", |channel| channel.name.as_str()),
+                    )
+                    .show_ui(ui, |ui| {
+                        for (index, channel) in channels.iter().enumerate() {
+                            ui.selectable_value(&mut self.source, index, &channel.name);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Window:");
+                egui::ComboBox::from_id_salt("fft_window")
+                    .selected_text(self.window.name())
+                    .show_ui(ui, |ui| {
+                        for window in Window::ALL {
+                            ui.selectable_value(&mut self.window, window, window.name());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Scale:");
+                egui::ComboBox::from_id_salt("fft_scale")
+                    .selected_text(self.scale.name())
+                    .show_ui(ui, |ui| {
+                        for scale in Scale::ALL {
+                            ui.selectable_value(&mut self.scale, scale, scale.name());
+                        }
+                    });
+                ui.end_row();
+
+                if self.scale == Scale::DbV {
+                    ui.label("Top:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.top_dbv)
+                            .speed(1.0)
+                            .range(-200.0..=100.0)
+                            .suffix(" dBV"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Scale:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.db_per_div)
+                            .speed(0.5)
+                            .range(1.0..=50.0)
+                            .suffix(" dB/div"),
+                    );
+                    ui.end_row();
+                }
+
+                ui.label("Harmonics:");
+                ui.add(egui::DragValue::new(&mut self.harmonics).range(2..=20));
+                ui.end_row();
+            });
+        });
+    }
+
+    /// Recomputes the spectrum if there is a new acquisition or the settings changed.
+    pub fn update(&mut self, channels: &[Channel], acquired: bool) {
+        let up_to_date = matches!(
+            self.spectrum,
+            Some((source, window, _)) if source == self.source && window == self.window
+        );
+        if !self.enabled || (up_to_date && !acquired) {
+            return;
+        }
+        self.spectrum = channels
+            .get(self.source)
+            .and_then(|channel| {
+                let trace = &channel.trace;
+                Spectrum::of(&trace[..trace.len().min(MAX_SAMPLES)], self.window)
+            })
+            .map(|spectrum| (self.source, self.window, spectrum));
+    }
+
+    /// Draws the spectrum with its grid, peak markers and harmonics readout into `rect`.
+    pub fn show(&self, display: &mut DisplayList, rect: egui::Rect, channels: &[Channel]) {
+        display.set_clip_rect(rect);
+        let font = egui::FontId::monospace(10.0);
+        let label_color = egui::Color32::from_gray(160);
+        let Some((source, _, spectrum)) = &self.spectrum else {
+            display.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "No spectrum",
+                font,
+                label_color,
+            );
+            return;
+        };
+        let color = channels
+            .get(*source)
+            .map_or(egui::Color32::WHITE, |channel| channel.color);
+
+        // Levels at the top and bottom of the grid, on the display scale
+        let (top, bottom) = match self.scale {
+            Scale::DbV => (self.top_dbv, self.top_dbv - self.db_per_div * VDIVS as f64),
+            Scale::Linear => {
+                let max = spectrum.vrms.iter().copied().fold(0.0, f64::max);
+                (nice_ceiling(max), 0.0)
+            }
+        };
+        let nyquist = spectrum.nyquist();
+        let x_for_frequency = |f: f64| rect.left() + (f / nyquist) as f32 * rect.width();
+        let y_for_level = |level: f64| {
+            let fraction = ((level - bottom) / (top - bottom)).clamp(-0.01, 1.01);
+            rect.bottom() - fraction as f32 * rect.height()
+        };
+
+        self.grid(display, rect, (top, bottom), nyquist);
+
+        // One point per pixel column, keeping the highest bin so narrow peaks stay visible
+        let columns = rect.width().max(1.0) as usize;
+        let bins_per_column = spectrum.vrms.len() as f64 / columns as f64;
+        let points: Vec<egui::Pos2> = if bins_per_column > 1.0 {
+            (0..columns)
+                .filter_map(|column| {
+                    let first = (column as f64 * bins_per_column) as usize;
+                    let end =
+                        (((column + 1) as f64 * bins_per_column) as usize).min(spectrum.vrms.len());
+                    let (k, vrms) = (first..end)
+                        .map(|k| (k, spectrum.vrms[k]))
+                        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+                    Some(egui::pos2(
+                        x_for_frequency(k as f64 * spectrum.bin_width),
+                        y_for_level(self.scale.apply(vrms)),
+                    ))
+                })
+                .collect()
+        } else {
+            spectrum
+                .vrms
+                .iter()
+                .enumerate()
+                .map(|(k, &vrms)| {
+                    egui::pos2(
+                        x_for_frequency(k as f64 * spectrum.bin_width),
+                        y_for_level(self.scale.apply(vrms)),
+                    )
+                })
+                .collect()
+        };
+        display.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+
+        for k in spectrum.peaks(PEAK_MARKERS) {
+            let frequency = k as f64 * spectrum.bin_width;
+            let tip = egui::pos2(
+                x_for_frequency(frequency),
+                y_for_level(self.scale.apply(spectrum.vrms[k])) - 3.0,
+            );
+            display.add(egui::Shape::convex_polygon(
+                vec![
+                    tip,
+                    tip + egui::vec2(4.0, -7.0),
+                    tip + egui::vec2(-4.0, -7.0),
+                ],
+                egui::Color32::WHITE,
+                egui::Stroke::NONE,
+            ));
+            display.text(
+                tip + egui::vec2(0.0, -9.0),
+                egui::Align2::CENTER_BOTTOM,
+                format_si(frequency, "Hz"),
+                font.clone(),
+                egui::Color32::WHITE,
+            );
+        }
+
+        self.readout(display, rect, spectrum, color);
+    }
+
+    fn grid(&self, display: &mut DisplayList, rect: egui::Rect, levels: (f64, f64), nyquist: f64) {
+        let (top, bottom) = levels;
+        let font = egui::FontId::monospace(10.0);
+        let label_color = egui::Color32::from_gray(160);
+        let stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(60));
+
+        for i in 0..=HDIVS {
+            let x = egui::lerp(rect.x_range(), i as f32 / HDIVS as f32);
+            display.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                stroke,
+            );
+            if i < HDIVS {
+                display.text(
+                    egui::pos2(x + 2.0, rect.bottom() - 2.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    format_si(nyquist * i as f64 / HDIVS as f64, "Hz"),
+                    font.clone(),
+                    label_color,
+                );
+            }
+        }
+        for j in 0..=VDIVS {
+            let y = egui::lerp(rect.y_range(), j as f32 / VDIVS as f32);
+            display.line_segment(
+                [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                stroke,
+            );
+            if j < VDIVS {
+                let level = top + (bottom - top) * j as f64 / VDIVS as f64;
+                let text = match self.scale {
+                    Scale::DbV => format!("{level:.0} dBV"),
+                    Scale::Linear => format_si(level, "V"),
+                };
+                display.text(
+                    egui::pos2(rect.left() + 2.0, y + 2.0),
+                    egui::Align2::LEFT_TOP,
+                    text,
+                    font.clone(),
+                    label_color,
+                );
+            }
+        }
+    }
+
+    /// Harmonic levels and THD in the top right corner.
+    fn readout(
+        &self,
+        display: &mut DisplayList,
+        rect: egui::Rect,
+        spectrum: &Spectrum,
+        color: egui::Color32,
+    ) {
+        let font = egui::FontId::monospace(11.0);
+        let harmonics = spectrum.harmonics(self.harmonics);
+        let mut lines: Vec<String> = harmonics
+            .iter()
+            .enumerate()
+            .map(|(index, harmonic)| {
+                let level = match self.scale {
+                    Scale::DbV => format!("{:.2} dBV", self.scale.apply(harmonic.vrms)),
+                    Scale::Linear => format_si(harmonic.vrms, "Vrms"),
+                };
+                format!(
+                    "H{:<2} {:>11} {level:>12}",
+                    index + 1,
+                    format_si(harmonic.frequency, "Hz")
+                )
+            })
+            .collect();
+        if let Some(thd) = thd(&harmonics) {
+            lines.push(format!(
+                "THD {:.3} % ({:.1} dB)",
+                thd * 100.0,
+                20.0 * thd.max(1e-12).log10()
+            ));
+        }
+
+        let mut pos = rect.right_top() + egui::vec2(-8.0, 8.0);
+        for line in lines {
+            pos.y += display
+                .text(pos, egui::Align2::RIGHT_TOP, line, font.clone(), color)
+                .height();
+        }
+    }
+}
+
+/// The smallest 1, 2 or 5 times a power of ten that is at least `value`.
+fn nice_ceiling(value: f64) -> f64 {
+    if !value.is_finite() || value <= 0.0 {
+        return 1.0;
+    }
+    let power = 10f64.powf(value.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|step| step * power)
+        .find(|&level| level >= value)
+        .unwrap_or(10.0 * power)
+}
@@ -0,0 +1,290 @@
+//! Loading recorded traces from files into channels.
+
+use std::sync::Arc;
+
+use crate::channel::{Channel, unused_number};
+use crate::signal::csv::{CsvOptions, Delimiter, TimeUnit, parse_csv};
+use crate::signal::{Recording, WavAudio};
+
+/// How files are imported. Remembered between imports.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct ImportSettings {
+    csv: CsvOptions,
+
+    /// Voltage (V) of a full-scale audio sample, until changed on the channel.
+    wav_full_scale: f64,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            csv: CsvOptions::default(),
+            wav_full_scale: 1.0,
+        }
+    }
+}
+
+enum Contents {
+    Csv(String),
+    Wav(WavAudio),
+
+    /// The file could not be read or decoded.
+    Unreadable,
+}
+
+/// A file the user opened, waiting for them to choose how to import it.
+pub(super) struct PendingImport {
+    name: String,
+    contents: Contents,
+
+    /// Index of the (first) channel to load the trace into.
+    channel: usize,
+
+    error: Option<String>,
+}
+
+impl PendingImport {
+    /// WAV files are recognized by their extension, anything else is treated as CSV.
+    pub fn new(name: impl Into<String>, bytes: &[u8]) -> Self {
+        let name = name.into();
+        if name.to_lowercase().ends_with(".wav") {
+            match WavAudio::decode(bytes) {
+                Ok(audio) => Self::with_contents(name, Contents::Wav(audio)),
+                Err(err) => Self::failed(name, format!("Not a supported WAV file: {err}")),
+            }
+        } else {
+            let text = String::from_utf8_lossy(bytes).into_owned();
+            Self::with_contents(name, Contents::Csv(text))
+        }
+    }
+
+    /// A file that could not be read.
+    pub fn failed(name: impl Into<String>, error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::with_contents(name.into(), Contents::Unreadable)
+        }
+    }
+
+    fn with_contents(name: String, contents: Contents) -> Self {
+        Self {
+            name,
+            contents,
+            channel: 0,
+            error: None,
+        }
+    }
+
+    /// Shows the import window. Returns false once it should close.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        settings: &mut ImportSettings,
+        channels: &mut Vec<Channel>,
+    ) -> bool {
+        let mut keep_open = true;
+        let title = match self.contents {
+            Contents::Wav(_) => "Import WAV",
+            Contents::Csv(_) | Contents::Unreadable => "Import CSV",
+        };
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("File: {}", self.name));
+
+                match &self.contents {
+                    Contents::Csv(text) => csv_options(ui, text, &mut settings.csv),
+                    Contents::Wav(audio) => wav_options(ui, audio, &mut settings.wav_full_scale),
+                    Contents::Unreadable => {}
+                }
+
+                egui::Grid::new("import_target").show(ui, |ui| {
+                    ui.label("Load into:");
+                    egui::ComboBox::from_id_salt("csv_channel")
+                        .selected_text(
+                            channels
+                                .get(self.channel)
+                                .map_or("", |channel| channel.name.as_str()),
+                        )
+                        .show_ui(ui, |ui| {
+                            for (index, channel) in channels.iter().enumerate() {
+                                if channel.math.is_none() {
+                                    ui.selectable_value(&mut self.channel, index, &channel.name);
+                                }
+                            }
+                        });
+                    ui.end_row();
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.horizontal(|ui| {
+                    let can_import = !matches!(self.contents, Contents::Unreadable);
+                    if ui
+                        .add_enabled(can_import, egui::Button::new("Import"))
+                        .clicked()
+                    {
+                        match self.import(settings, channels) {
+                            Ok(()) => keep_open = false,
+                            Err(error) => self.error = Some(error),
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        keep_open = false;
+                    }
+                });
+            });
+        keep_open
+    }
+
+    /// Loads the file into the target channel(s).
+    fn import(&self, settings: &ImportSettings, channels: &mut Vec<Channel>) -> Result<(), String> {
+        let recordings = match &self.contents {
+            Contents::Csv(text) => {
+                let samples = parse_csv(text, &settings.csv).map_err(|err| err.to_string())?;
+                let recording = Recording::new(self.name.clone(), samples)
+                    .ok_or("Need at least two samples at different times")?;
+                vec![recording]
+            }
+            Contents::Wav(audio) => audio
+                .channels
+                .iter()
+                .enumerate()
+                .map(|(index, samples)| {
+                    let name = if audio.channels.len() > 1 {
+                        format!("{} [{}]", self.name, index + 1)
+                    } else {
+                        self.name.clone()
+                    };
+                    Recording::uniform(
+                        name,
+                        f64::from(audio.sample_rate),
+                        settings.wav_full_scale,
+                        samples.clone(),
+                    )
+                    .ok_or("Need at least two samples")
+                })
+                .collect::<Result<_, _>>()?,
+            Contents::Unreadable => return Err("Nothing to import".to_owned()),
+        };
+
+        // Audio channel k goes to the k-th input channel from the target on, adding
+        // channels as needed. Math channels in between are left alone.
+        let mut targets = (self.channel..channels.len())
+            .filter(|&index| channels[index].math.is_none())
+            .collect::<Vec<_>>()
+            .into_iter();
+        for recording in recordings {
+            let target = targets.next().unwrap_or_else(|| {
+                channels.push(Channel::new(unused_number(channels, false)));
+                channels.len() - 1
+            });
+            channels[target].recording = Some(Arc::new(recording));
+            channels[target].enabled = true;
+        }
+        Ok(())
+    }
+}
+
+fn csv_options(ui: &mut egui::Ui, text: &str, options: &mut CsvOptions) {
+    // The first few lines, to help pick columns and header lines
+    let preview: Vec<&str> = text.lines().take(6).collect();
+    ui.add(egui::Label::new(egui::RichText::new(preview.join("\n")).monospace()).truncate());
+
+    ui.separator();
+
+    egui::Grid::new("csv_options").show(ui, |ui| {
+        ui.label("Time column:");
+        column_index(ui, &mut options.time_column);
+        ui.end_row();
+
+        ui.label("Voltage column:");
+        column_index(ui, &mut options.voltage_column);
+        ui.end_row();
+
+        ui.label("Header lines to skip:");
+        ui.add(egui::DragValue::new(&mut options.skip_lines).range(0..=1000));
+        ui.end_row();
+
+        ui.label("Delimiter:");
+        egui::ComboBox::from_id_salt("csv_delimiter")
+            .selected_text(options.delimiter.name())
+            .show_ui(ui, |ui| {
+                for delimiter in Delimiter::ALL {
+                    ui.selectable_value(&mut options.delimiter, delimiter, delimiter.name());
+                }
+            });
+        ui.end_row();
+
+        ui.label("Time unit:");
+        egui::ComboBox::from_id_salt("csv_time_unit")
+            .selected_text(options.time_unit.name())
+            .show_ui(ui, |ui| {
+                for unit in TimeUnit::ALL {
+                    ui.selectable_value(&mut options.time_unit, unit, unit.name());
+                }
+            });
+        ui.end_row();
+    });
+}
+
+fn wav_options(ui: &mut egui::Ui, audio: &WavAudio, full_scale: &mut f64) {
+    ui.label(format!(
+        "{} channel(s), {} Hz, {}-bit {}, {:.2} s",
+        audio.channels.len(),
+        audio.sample_rate,
+        audio.bits_per_sample,
+        if audio.is_float { "float" } else { "PCM" },
+        audio.duration()
+    ));
+
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        ui.label("Full scale:");
+        ui.add(
+            egui::DragValue::new(full_scale)
+                .speed(0.01)
+                .range(0.001..=1000.0)
+                .suffix(" V"),
+        );
+    });
+}
+
+/// Edits a zero-based column index, shown one-based.
+fn column_index(ui: &mut egui::Ui, index: &mut usize) {
+    let mut column = *index + 1;
+    if ui
+        .add(egui::DragValue::new(&mut column).range(1..=100))
+        .changed()
+    {
+        *index = column - 1;
+    }
+}
+
+/// Reads a file dropped onto the window.
+pub(super) fn read_dropped_file(file: &egui::DroppedFile) -> PendingImport {
+    if let Some(bytes) = &file.bytes {
+        return PendingImport::new(file.name.clone(), bytes);
+    }
+    match &file.path {
+        Some(path) => read_path(path),
+        None => PendingImport::failed(file.name.clone(), "File has no contents".to_owned()),
+    }
+}
+
+/// Reads a file from disk (not available on the web).
+pub(super) fn read_path(path: &std::path::Path) -> PendingImport {
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    match std::fs::read(path) {
+        Ok(bytes) => PendingImport::new(name, &bytes),
+        Err(err) => PendingImport::failed(name, err.to_string()),
+    }
+}
@@ -0,0 +1,371 @@
+//! Display persistence: trace hits from successive acquisitions accumulate in an intensity
+//! buffer per channel, shown as a colour-graded texture under the live traces.
+
+use std::sync::Arc;
+
+use super::capture::DisplayList;
+use super::waterfall::ColorMap;
+use super::{ScreenGrid, screen_points};
+use crate::channel::Channel;
+use crate::interpolation::Interpolation;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct Persistence {
+    pub enabled: bool,
+
+    /// Never fade: keep every hit until cleared.
+    pub infinite: bool,
+
+    /// Time constant (s) of the fading.
+    pub decay: f64,
+
+    /// Colour grading of the intensity. `None` uses each channel's colour.
+    pub color_map: Option<ColorMap>,
+
+    /// Buffer size in pixels: one pixel per point of the scope area.
+    #[serde(skip)]
+    size: [usize; 2],
+
+    /// Screen mapping the buffers were drawn with. Any change clears them.
+    #[serde(skip)]
+    view: View,
+
+    /// Volts per division and offset of each channel the buffers were drawn with. Any
+    /// change clears them.
+    #[serde(skip)]
+    scales: Vec<(f64, f64)>,
+
+    /// One per channel, by index.
+    #[serde(skip)]
+    layers: Vec<Layer>,
+
+    /// Pixels hit by the trace being added, so each acquisition counts once per pixel.
+    #[serde(skip)]
+    hits: Vec<bool>,
+
+    /// The textures are out of date.
+    #[serde(skip)]
+    dirty: bool,
+}
+
+/// Where the buffers' pixels are in time and divisions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct View {
+    /// Centre of the graticule from the top left of the buffer, in pixels.
+    origin: egui::Vec2,
+    cell_size: f32,
+    secs_per_div: f64,
+}
+
+#[derive(Default)]
+struct Layer {
+    /// Accumulated hits of each pixel, row by row.
+    intensity: Vec<f32>,
+    texture: Option<(egui::TextureHandle, Arc<egui::ColorImage>)>,
+}
+
+impl Default for Persistence {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            infinite: false,
+            decay: 1.0,
+            color_map: None,
+            size: [0, 0],
+            view: View::default(),
+            scales: Vec::new(),
+            layers: Vec::new(),
+            hits: Vec::new(),
+            dirty: false,
+        }
+    }
+}
+
+impl Persistence {
+    /// Side-panel controls.
+    pub fn panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Persistence").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.enabled, "Persistence");
+                if ui.button("Clear").clicked() {
+                    self.clear();
+                }
+            });
+            ui.checkbox(&mut self.infinite, "Infinite");
+            ui.add_enabled_ui(!self.infinite, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Decay:");
+                    ui.add(
+                        egui::Slider::new(&mut self.decay, 0.05..=20.0)
+                            .logarithmic(true)
+                            .suffix(" s"),
+                    );
+                });
+            });
+            let color_map = self.color_map;
+            ui.horizontal(|ui| {
+                ui.label("Colour:");
+                let name = self.color_map.map_or("Channel colour", ColorMap::name);
+                egui::ComboBox::from_id_salt("persistence_color_map")
+                    .selected_text(name)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.color_map, None, "Channel colour");
+                        for map in ColorMap::ALL {
+                            ui.selectable_value(&mut self.color_map, Some(map), map.name());
+                        }
+                    });
+            });
+            self.dirty |= self.color_map != color_map;
+        });
+    }
+
+    pub fn clear(&mut self) {
+        for layer in &mut self.layers {
+            layer.intensity.fill(0.0);
+        }
+        self.dirty = true;
+    }
+
+    /// Fades the buffers by `dt` seconds of display time, unless persistence is infinite.
+    pub fn fade(&mut self, dt: f64) {
+        if self.infinite {
+            return;
+        }
+        let fade = (-dt / self.decay).exp() as f32;
+        for layer in &mut self.layers {
+            layer.intensity.iter_mut().for_each(|i| *i *= fade);
+        }
+        self.dirty = true;
+    }
+
+    /// Adds the traces of a new acquisition, if `acquired`, drawn with `interpolation`
+    /// between the samples.
+    ///
+    /// The buffers are in screen space, so a change of size, zoom, pan or scale clears them.
+    pub fn update(
+        &mut self,
+        grid: &ScreenGrid,
+        secs_per_div: f64,
+        channels: &[Channel],
+        interpolation: Interpolation,
+        acquired: bool,
+    ) {
+        let size = [
+            grid.rect.width().max(1.0) as usize,
+            grid.rect.height().max(1.0) as usize,
+        ];
+        let view = View {
+            origin: grid.origin - grid.rect.min,
+            cell_size: grid.cell_size,
+            secs_per_div,
+        };
+        let scales = || channels.iter().map(|c| (c.volts_per_div, c.offset));
+        if size != self.size || view != self.view || !self.scales.iter().copied().eq(scales()) {
+            self.size = size;
+            self.view = view;
+            self.scales = scales().collect();
+            self.layers.resize_with(channels.len(), Layer::default);
+            for layer in &mut self.layers {
+                layer.intensity = vec![0.0; size[0] * size[1]];
+            }
+            self.dirty = true;
+        }
+        if !acquired {
+            return;
+        }
+
+        for (layer, channel) in self.layers.iter_mut().zip(channels) {
+            if !channel.enabled {
+                continue;
+            }
+            self.hits.clear();
+            self.hits.resize(size[0] * size[1], false);
+            let pixels = screen_points(channel, grid, secs_per_div, interpolation)
+                .into_iter()
+                .map(|p| p - grid.rect.min.to_vec2());
+            rasterize(pixels, size, &mut self.hits);
+            for (intensity, _) in layer
+                .intensity
+                .iter_mut()
+                .zip(&self.hits)
+                .filter(|(_, h)| **h)
+            {
+                *intensity += 1.0;
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Draws the enabled channels' buffers over the scope area.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        display: &mut DisplayList,
+        rect: egui::Rect,
+        channels: &[Channel],
+    ) {
+        let rendered = self.size;
+        for (index, (layer, channel)) in self.layers.iter_mut().zip(channels).enumerate() {
+            if !channel.enabled {
+                continue;
+            }
+            if self.dirty || layer.texture.is_none() {
+                let image = Arc::new(render(&layer.intensity, rendered, |x| {
+                    self.color_map.map_or_else(
+                        || channel.color.gamma_multiply(x),
+                        |map| map.color(f64::from(x)),
+                    )
+                }));
+                if let Some((texture, current)) = &mut layer.texture {
+                    texture.set(Arc::clone(&image), egui::TextureOptions::NEAREST);
+                    *current = image;
+                } else {
+                    let texture = ctx.load_texture(
+                        format!("persistence{index}"),
+                        Arc::clone(&image),
+                        egui::TextureOptions::NEAREST,
+                    );
+                    layer.texture = Some((texture, image));
+                }
+            }
+            if let Some((texture, image)) = &layer.texture {
+                let size = egui::vec2(rendered[0] as f32, rendered[1] as f32);
+                display.image(
+                    texture,
+                    egui::Rect::from_min_size(rect.min, size),
+                    Arc::clone(image),
+                );
+            }
+        }
+        self.dirty = false;
+    }
+}
+
+/// Marks the pixels along the polyline through `points`, which are in pixels from the top
+/// left of a `size` buffer. Points off the buffer are pulled in to just outside its edges.
+fn rasterize(points: impl Iterator<Item = egui::Pos2>, size: [usize; 2], hits: &mut [bool]) {
+    let [width, height] = size;
+    let limit = |p: egui::Pos2| {
+        egui::pos2(
+            p.x.clamp(-1.0, width as f32),
+            p.y.clamp(-1.0, height as f32),
+        )
+    };
+    let mut previous: Option<egui::Pos2> = None;
+    for point in points.map(limit) {
+        let start = previous.unwrap_or(point);
+        previous = Some(point);
+        let delta = point - start;
+        let steps = delta.x.abs().max(delta.y.abs()).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let p = start + delta * (step as f32 / steps as f32);
+            if p.x >= 0.0 && p.y >= 0.0 && (p.x as usize) < width && (p.y as usize) < height {
+                hits[p.y as usize * width + p.x as usize] = true;
+            }
+        }
+    }
+}
+
+/// Colours the intensity buffer, on a log scale relative to its brightest pixel. Pixels
+/// never hit are transparent.
+fn render(
+    intensity: &[f32],
+    size: [usize; 2],
+    color: impl Fn(f32) -> egui::Color32,
+) -> egui::ColorImage {
+    let max = intensity.iter().copied().fold(0.0, f32::max);
+    let scale = 1.0 / max.ln_1p();
+    let pixels = intensity
+        .iter()
+        .map(|&i| {
+            // Faded out below one hundredth of a hit
+            if i < 0.01 {
+                egui::Color32::TRANSPARENT
+            } else {
+                color((i.ln_1p() * scale).clamp(0.05, 1.0))
+            }
+        })
+        .collect();
+    egui::ColorImage::new(size, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::LN_2;
+
+    use super::{Layer, Persistence, rasterize};
+
+    fn hits(points: &[(f32, f32)], size: [usize; 2]) -> Vec<bool> {
+        let mut hits = vec![false; size[0] * size[1]];
+        rasterize(
+            points.iter().map(|&(x, y)| egui::pos2(x, y)),
+            size,
+            &mut hits,
+        );
+        hits
+    }
+
+    fn persistence() -> Persistence {
+        Persistence {
+            decay: 2.0,
+            layers: vec![Layer {
+                intensity: vec![1.0; 4],
+                texture: None,
+            }],
+            ..Persistence::default()
+        }
+    }
+
+    #[test]
+    fn lines_are_contiguous() {
+        // A steep line: one pixel in every row, each next to the one above
+        let size = [10, 10];
+        let hits = hits(&[(1.5, 0.5), (4.5, 9.5)], size);
+        let columns: Vec<usize> = (0..size[1])
+            .map(|y| {
+                let row = &hits[y * size[0]..(y + 1) * size[0]];
+                let hit: Vec<usize> = (0..size[0]).filter(|&x| row[x]).collect();
+                assert!(!hit.is_empty(), "row {y} is hit");
+                hit[0]
+            })
+            .collect();
+        assert!(
+            columns.windows(2).all(|c| c[1].abs_diff(c[0]) <= 1),
+            "no gaps: {columns:?}"
+        );
+        assert_eq!((columns[0], columns[9]), (1, 4), "ends");
+    }
+
+    #[test]
+    fn points_off_the_buffer_are_clipped() {
+        let size = [4, 4];
+        let across = hits(&[(-100.0, 1.5), (100.0, 1.5)], size);
+        let expected: Vec<bool> = (0..16).map(|i| i / 4 == 1).collect();
+        assert_eq!(across, expected, "the row across the buffer");
+
+        let above = hits(&[(-100.0, -5.0), (100.0, -50.0)], size);
+        assert!(above.iter().all(|h| !h), "nothing off its edges");
+    }
+
+    #[test]
+    fn decay_halves_the_intensity_in_its_half_life() {
+        let mut persistence = persistence();
+        persistence.fade(2.0 * LN_2);
+        for &i in &persistence.layers[0].intensity {
+            assert!((i - 0.5).abs() < 1e-6, "halved: {i}");
+        }
+
+        persistence.infinite = true;
+        persistence.fade(100.0);
+        for &i in &persistence.layers[0].intensity {
+            assert!((i - 0.5).abs() < 1e-6, "infinite persistence keeps it: {i}");
+        }
+
+        persistence.clear();
+        assert!(
+            persistence.layers[0].intensity.iter().all(|&i| i == 0.0),
+            "cleared"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Saving generated files: a save dialog on native, a browser download on the web.
+
+/// Asks where to save `bytes`, suggesting `file_name`. Errors are logged.
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn save_file(file_name: &str, _mime: &str, bytes: &[u8]) {
+    let extension = file_name.rsplit('.').next().unwrap_or_default();
+    let Some(path) = rfd::FileDialog::new()
+        .set_file_name(file_name)
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file()
+    else {
+        return;
+    };
+    if let Err(err) = std::fs::write(&path, bytes) {
+        log::error!("Failed to write {}: {err}", path.display());
+    }
+}
+
+/// Downloads `bytes` as `file_name`. Errors are logged.
+#[cfg(target_arch = "wasm32")]
+pub(super) fn save_file(file_name: &str, mime: &str, bytes: &[u8]) {
+    if let Err(err) = download(file_name, mime, bytes) {
+        log::error!("Failed to download {file_name}: {err:?}");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn download(
+    file_name: &str,
+    mime: &str,
+    bytes: &[u8],
+) -> Result<(), eframe::wasm_bindgen::JsValue> {
+    use eframe::wasm_bindgen::JsCast as _;
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("No document")?;
+    let anchor = document
+        .create_element("a")?
+        .dyn_into::<web_sys::HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url)
+}
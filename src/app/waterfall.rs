@@ -0,0 +1,440 @@
+//! Waterfall view: successive short-time spectra stacked as a colour-mapped texture, newest
+//! at the top.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::capture::DisplayList;
+use crate::channel::Channel;
+use crate::measure::format_si;
+use crate::spectrum::{Scale, Window, spectrogram};
+
+const FFT_SIZES: [usize; 7] = [64, 128, 256, 512, 1024, 2048, 4096];
+
+/// Number of spectra kept, one texture row each.
+const HISTORY: usize = 256;
+
+/// Most FFT frames averaged into the row of one acquisition.
+const FRAMES_PER_ROW: usize = 16;
+
+/// Frequency divisions marked along the bottom.
+const HDIVS: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(super) enum ColorMap {
+    Viridis,
+    Inferno,
+    Jet,
+    Grayscale,
+}
+
+impl ColorMap {
+    pub const ALL: [Self; 4] = [Self::Viridis, Self::Inferno, Self::Jet, Self::Grayscale];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Viridis => "Viridis",
+            Self::Inferno => "Inferno",
+            Self::Jet => "Jet",
+            Self::Grayscale => "Grayscale",
+        }
+    }
+
+    /// Evenly spaced colours from the low to the high end of the map.
+    fn stops(self) -> &'static [[u8; 3]] {
+        match self {
+            Self::Viridis => &[
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+            Self::Inferno => &[
+                [0, 0, 4],
+                [87, 16, 110],
+                [188, 55, 84],
+                [249, 142, 9],
+                [252, 255, 164],
+            ],
+            Self::Jet => &[
+                [0, 0, 128],
+                [0, 0, 255],
+                [0, 255, 255],
+                [255, 255, 0],
+                [255, 0, 0],
+                [128, 0, 0],
+            ],
+            Self::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        }
+    }
+
+    /// Colour at `x` in `0..=1`, interpolated between the stops.
+    pub fn color(self, x: f64) -> egui::Color32 {
+        let stops = self.stops();
+        let position = x.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f64;
+        let [r, g, b] = [0, 1, 2].map(|c| {
+            let (a, b) = (f64::from(stops[index][c]), f64::from(stops[index + 1][c]));
+            (a + (b - a) * fraction).round() as u8
+        });
+        egui::Color32::from_rgb(r, g, b)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct Waterfall {
+    pub enabled: bool,
+
+    /// Index of the channel to analyse.
+    pub source: usize,
+
+    /// Samples per FFT frame, one of [`FFT_SIZES`].
+    pub fft_size: usize,
+
+    /// Fraction of each frame shared with the next one.
+    pub overlap: f64,
+
+    pub color_map: ColorMap,
+
+    /// dBV shown with the low and high ends of the colour map.
+    pub min_dbv: f64,
+    pub max_dbv: f64,
+
+    /// dBV of each bin of the kept spectra, newest first.
+    #[serde(skip)]
+    rows: VecDeque<Vec<f64>>,
+    #[serde(skip)]
+    bin_width: f64,
+
+    #[serde(skip)]
+    texture: Option<(egui::TextureHandle, Arc<egui::ColorImage>)>,
+
+    /// Colour map and range the texture was rendered with. `None` once new rows come in.
+    #[serde(skip)]
+    rendered: Option<(ColorMap, f64, f64)>,
+}
+
+impl Default for Waterfall {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: 0,
+            fft_size: 256,
+            overlap: 0.5,
+            color_map: ColorMap::Viridis,
+            min_dbv: -100.0,
+            max_dbv: 20.0,
+            rows: VecDeque::new(),
+            bin_width: 0.0,
+            texture: None,
+            rendered: None,
+        }
+    }
+}
+
+impl Waterfall {
+    /// Side-panel controls.
+    pub fn panel(&mut self, ui: &mut egui::Ui, channels: &[Channel]) {
+        egui::CollapsingHeader::new("Waterfall").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.enabled, "Show waterfall");
+                if ui.button("Clear").clicked() {
+                    self.rows.clear();
+                    self.rendered = None;
+                }
+            });
+            egui::Grid::new("waterfall_settings").show(ui, |ui| {
+                ui.label("Source:");
+                egui::ComboBox::from_id_salt("waterfall_source")
+                    .selected_text(
+                        channels
+                            .get(self.source)
+                            .map_or("", |channel| channel.name.as_str()),
+                    )
+                    .show_ui(ui, |ui| {
+                        for (index, channel) in channels.iter().enumerate() {
+                            ui.selectable_value(&mut self.source, index, &channel.name);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("FFT size:");
+                egui::ComboBox::from_id_salt("waterfall_fft_size")
+                    .selected_text(self.fft_size.to_string())
+                    .show_ui(ui, |ui| {
+                        for size in FFT_SIZES {
+                            ui.selectable_value(&mut self.fft_size, size, size.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Overlap:");
+                let mut percent = self.overlap * 100.0;
+                if ui
+                    .add(egui::Slider::new(&mut percent, 0.0..=90.0).suffix(" %"))
+                    .changed()
+                {
+                    self.overlap = percent / 100.0;
+                }
+                ui.end_row();
+
+                ui.label("Colour map:");
+                egui::ComboBox::from_id_salt("waterfall_color_map")
+                    .selected_text(self.color_map.name())
+                    .show_ui(ui, |ui| {
+                        for map in ColorMap::ALL {
+                            ui.selectable_value(&mut self.color_map, map, map.name());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Range:");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.min_dbv)
+                            .speed(1.0)
+                            .range(-200.0..=self.max_dbv - 1.0)
+                            .suffix(" dBV"),
+                    );
+                    ui.label("to");
+                    ui.add(
+                        egui::DragValue::new(&mut self.max_dbv)
+                            .speed(1.0)
+                            .range(self.min_dbv + 1.0..=100.0)
+                            .suffix(" dBV"),
+                    );
+                });
+                ui.end_row();
+            });
+        });
+    }
+
+    /// Adds one row for a new acquisition: the power average of up to [`FRAMES_PER_ROW`]
+    /// overlapping frames from the end of the record, so the vertical axis is time between
+    /// acquisitions. A change of FFT size or sample rate starts a new history.
+    pub fn update(&mut self, channels: &[Channel], acquired: bool) {
+        if !self.enabled || !acquired {
+            return;
+        }
+        let Some(channel) = channels.get(self.source) else {
+            return;
+        };
+        let hop = ((self.fft_size as f64 * (1.0 - self.overlap)).round() as usize).max(1);
+        let trace = &channel.trace;
+        let tail = &trace[trace
+            .len()
+            .saturating_sub(self.fft_size + hop * (FRAMES_PER_ROW - 1))..];
+        let frames = spectrogram(tail, self.fft_size, self.overlap, Window::Hann);
+        let Some(first) = frames.first() else {
+            return;
+        };
+        let same_bins = self
+            .rows
+            .front()
+            .is_some_and(|row| row.len() == first.vrms.len());
+        if !same_bins || (first.bin_width - self.bin_width).abs() > 1e-9 * self.bin_width {
+            self.rows.clear();
+            self.bin_width = first.bin_width;
+        }
+        let row = (0..first.vrms.len())
+            .map(|k| {
+                let mean_square = frames
+                    .iter()
+                    .map(|frame| frame.vrms[k].powi(2))
+                    .sum::<f64>()
+                    / frames.len() as f64;
+                Scale::DbV.apply(mean_square.sqrt())
+            })
+            .collect();
+        self.rows.push_front(row);
+        self.rows.truncate(HISTORY);
+        self.rendered = None;
+    }
+
+    /// Draws the waterfall into `rect`, with frequency labels and a colour scale.
+    pub fn show(&mut self, ctx: &egui::Context, display: &mut DisplayList, rect: egui::Rect) {
+        display.set_clip_rect(rect);
+        let font = egui::FontId::monospace(10.0);
+        let label_color = egui::Color32::from_gray(200);
+        let Some(bins) = self.rows.front().map(Vec::len) else {
+            display.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "No spectra",
+                font,
+                label_color,
+            );
+            return;
+        };
+
+        let settings = Some((self.color_map, self.min_dbv, self.max_dbv));
+        if self.rendered != settings {
+            let image = Arc::new(self.render(bins));
+            if let Some((texture, current)) = &mut self.texture {
+                texture.set(Arc::clone(&image), egui::TextureOptions::LINEAR);
+                *current = image;
+            } else {
+                let texture = ctx.load_texture(
+                    "waterfall",
+                    Arc::clone(&image),
+                    egui::TextureOptions::LINEAR,
+                );
+                self.texture = Some((texture, image));
+            }
+            self.rendered = settings;
+        }
+        if let Some((texture, image)) = &self.texture {
+            display.image(texture, rect, Arc::clone(image));
+        }
+
+        // Frequency marks along the bottom
+        let nyquist = self.bin_width * (bins - 1) as f64;
+        let stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(200));
+        for i in 0..HDIVS {
+            let x = egui::lerp(rect.x_range(), i as f32 / HDIVS as f32);
+            display.line_segment(
+                [
+                    egui::pos2(x, rect.bottom() - 6.0),
+                    egui::pos2(x, rect.bottom()),
+                ],
+                stroke,
+            );
+            display.text(
+                egui::pos2(x + 2.0, rect.bottom() - 2.0),
+                egui::Align2::LEFT_BOTTOM,
+                format_si(nyquist * i as f64 / HDIVS as f64, "Hz"),
+                font.clone(),
+                label_color,
+            );
+        }
+
+        self.color_scale(display, rect, &font);
+    }
+
+    /// Colour bar in the top right corner, labelled with the dB range.
+    fn color_scale(&self, display: &mut DisplayList, rect: egui::Rect, font: &egui::FontId) {
+        let steps = 32;
+        let bar = egui::Rect::from_min_size(
+            rect.right_top() + egui::vec2(-20.0, 8.0),
+            egui::vec2(10.0, 96.0),
+        );
+        for i in 0..steps {
+            let top = egui::lerp(bar.y_range(), i as f32 / steps as f32);
+            let bottom = egui::lerp(bar.y_range(), (i + 1) as f32 / steps as f32);
+            display.add(egui::Shape::rect_filled(
+                egui::Rect::from_x_y_ranges(bar.x_range(), top..=bottom),
+                0.0,
+                self.color_map
+                    .color(1.0 - (f64::from(i) + 0.5) / f64::from(steps)),
+            ));
+        }
+        let text_color = egui::Color32::from_gray(200);
+        for (pos, anchor, dbv) in [
+            (bar.left_top(), egui::Align2::RIGHT_TOP, self.max_dbv),
+            (bar.left_bottom(), egui::Align2::RIGHT_BOTTOM, self.min_dbv),
+        ] {
+            display.text(
+                pos - egui::vec2(4.0, 0.0),
+                anchor,
+                format!("{dbv:.0} dBV"),
+                font.clone(),
+                text_color,
+            );
+        }
+    }
+
+    /// One row per kept spectrum, newest at the top. Rows not filled yet are transparent.
+    fn render(&self, bins: usize) -> egui::ColorImage {
+        let mut image = egui::ColorImage::filled([bins, HISTORY], egui::Color32::TRANSPARENT);
+        let span = self.max_dbv - self.min_dbv;
+        for (row, dbv) in image.pixels.chunks_mut(bins).zip(&self.rows) {
+            for (pixel, &level) in row.iter_mut().zip(dbv) {
+                *pixel = self.color_map.color((level - self.min_dbv) / span);
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorMap, HISTORY, Waterfall};
+    use crate::channel::Channel;
+    use crate::signal::Sample;
+
+    /// Channel 1 with a record of 1 V sine at a tenth of the sample rate, `dt` (s) apart.
+    fn channels(dt: f64) -> Vec<Channel> {
+        let mut channel = Channel::new(0);
+        channel.trace = (0..1000)
+            .map(|i| Sample {
+                t: f64::from(i) * dt,
+                v: (std::f64::consts::TAU * f64::from(i) / 10.0).sin(),
+            })
+            .collect();
+        vec![channel]
+    }
+
+    fn waterfall() -> Waterfall {
+        Waterfall {
+            enabled: true,
+            ..Waterfall::default()
+        }
+    }
+
+    #[test]
+    fn color_map_ends_midpoint_and_clamping() {
+        let map = ColorMap::Grayscale;
+        assert_eq!(map.color(0.0), egui::Color32::BLACK, "low end");
+        assert_eq!(map.color(1.0), egui::Color32::WHITE, "high end");
+        assert_eq!(map.color(0.5), egui::Color32::from_gray(128), "midpoint");
+        assert_eq!(map.color(-1.0), map.color(0.0), "clamped below");
+        assert_eq!(map.color(2.0), map.color(1.0), "clamped above");
+
+        assert_eq!(
+            ColorMap::Viridis.color(0.5),
+            egui::Color32::from_rgb(33, 145, 140),
+            "midpoint on a stop"
+        );
+        assert_eq!(
+            ColorMap::Jet.color(1.0),
+            egui::Color32::from_rgb(128, 0, 0),
+            "last stop"
+        );
+    }
+
+    #[test]
+    fn new_bin_width_restarts_the_history() {
+        let mut waterfall = waterfall();
+        for _ in 0..3 {
+            waterfall.update(&channels(1e-3), true);
+        }
+        waterfall.update(&channels(1e-3), false);
+        assert_eq!(waterfall.rows.len(), 3, "one row per acquisition");
+        assert!(
+            (waterfall.bin_width - 1e3 / 256.0).abs() < 1e-9,
+            "bin width {}",
+            waterfall.bin_width
+        );
+
+        waterfall.update(&channels(2e-3), true);
+        assert_eq!(waterfall.rows.len(), 1, "restarted at the new sample rate");
+        assert!(
+            (waterfall.bin_width - 500.0 / 256.0).abs() < 1e-9,
+            "bin width {}",
+            waterfall.bin_width
+        );
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut waterfall = waterfall();
+        let channels = channels(1e-3);
+        for _ in 0..HISTORY + 10 {
+            waterfall.update(&channels, true);
+        }
+        assert_eq!(waterfall.rows.len(), HISTORY, "oldest rows dropped");
+    }
+}
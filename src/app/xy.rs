@@ -0,0 +1,192 @@
+//! XY mode: one channel against another, for Lissajous figures and phase checks.
+
+use std::collections::VecDeque;
+
+use super::ScreenGrid;
+use super::capture::DisplayList;
+use crate::channel::Channel;
+
+/// Points drawn per acquisition; longer records are thinned out evenly.
+const MAX_POINTS: usize = 20_000;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct Xy {
+    pub enabled: bool,
+
+    /// Index of the channel on the horizontal axis.
+    pub x: usize,
+
+    /// Index of the channel on the vertical axis.
+    pub y: usize,
+
+    /// Number of earlier acquisitions kept on screen, fading with age.
+    pub persistence: usize,
+
+    /// `(x, y)` volts of each kept acquisition, newest first.
+    #[serde(skip)]
+    history: VecDeque<Vec<(f64, f64)>>,
+
+    /// The channels the history was taken from.
+    #[serde(skip)]
+    history_sources: (usize, usize),
+}
+
+impl Default for Xy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            x: 0,
+            y: 1,
+            persistence: 8,
+            history: VecDeque::new(),
+            history_sources: (0, 1),
+        }
+    }
+}
+
+impl Xy {
+    /// Side-panel controls.
+    pub fn panel(&mut self, ui: &mut egui::Ui, channels: &[Channel]) {
+        egui::CollapsingHeader::new("XY mode").show(ui, |ui| {
+            ui.checkbox(&mut self.enabled, "XY display");
+            egui::Grid::new("xy_settings").show(ui, |ui| {
+                for (label, axis, id) in [("X:", &mut self.x, "xy_x"), ("Y:", &mut self.y, "xy_y")]
+                {
+                    ui.label(label);
+                    egui::ComboBox::from_id_salt(id)
+                        .selected_text(
+                            channels
+                                .get(*axis)
+                                .map_or("", |channel| channel.name.as_str()),
+                        )
+                        .show_ui(ui, |ui| {
+                            for (index, channel) in channels.iter().enumerate() {
+                                ui.selectable_value(axis, index, &channel.name);
+                            }
+                        });
+                    ui.end_row();
+                }
+
+                ui.label("Persistence:");
+                ui.add(egui::Slider::new(&mut self.persistence, 0..=50).suffix(" acq."))
+                    .on_hover_text("Earlier acquisitions kept on screen, dimmer with age");
+                ui.end_row();
+            });
+        });
+    }
+
+    /// Drops the kept acquisitions.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Adds a new acquisition to the history.
+    pub fn update(&mut self, channels: &[Channel], acquired: bool) {
+        if (self.x, self.y) != self.history_sources {
+            self.history.clear();
+            self.history_sources = (self.x, self.y);
+        }
+        self.history.truncate(self.persistence + 1);
+        if !acquired {
+            return;
+        }
+        let (Some(x), Some(y)) = (channels.get(self.x), channels.get(self.y)) else {
+            return;
+        };
+        let step = x.trace.len().div_ceil(MAX_POINTS).max(1);
+        let points = x
+            .trace
+            .iter()
+            .zip(&y.trace)
+            .step_by(step)
+            .map(|(x, y)| (x.v, y.v))
+            .collect();
+        self.history.push_front(points);
+        self.history.truncate(self.persistence + 1);
+    }
+
+    /// Draws the kept acquisitions, oldest and dimmest first, with each axis on its channel's
+    /// scale, and the axis assignment in the top left.
+    pub fn show(&self, display: &mut DisplayList, grid: &ScreenGrid, channels: &[Channel]) {
+        let (Some(x), Some(y)) = (channels.get(self.x), channels.get(self.y)) else {
+            return;
+        };
+        let layers = self.history.len();
+        for (age, points) in self.history.iter().enumerate().rev() {
+            let brightness = 1.0 - age as f32 / layers as f32;
+            let points = points
+                .iter()
+                .map(|&(vx, vy)| {
+                    egui::pos2(
+                        grid.x_for_divisions(x.to_divisions(vx)),
+                        grid.y_for_divisions(y.to_divisions(vy)),
+                    )
+                })
+                .collect();
+            display.add(egui::Shape::line(
+                points,
+                egui::Stroke::new(2.0, y.color.gamma_multiply(brightness)),
+            ));
+        }
+
+        let font = egui::FontId::monospace(11.0);
+        let mut pos = grid.rect.left_top() + egui::vec2(24.0, 24.0);
+        for (axis, channel) in [("X", x), ("Y", y)] {
+            pos.y += display
+                .text(
+                    pos,
+                    egui::Align2::LEFT_TOP,
+                    format!(
+                        "{axis}: {} {:.2} {}/div",
+                        channel.name,
+                        channel.volts_per_div,
+                        channel.unit()
+                    ),
+                    font.clone(),
+                    channel.color,
+                )
+                .height();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_POINTS, Xy};
+    use crate::channel::Channel;
+    use crate::signal::Sample;
+
+    #[test]
+    fn history_is_capped_and_thinned() {
+        let mut channels = vec![Channel::new(0), Channel::new(1)];
+        for channel in &mut channels {
+            channel.trace = (0..50_000)
+                .map(|i| Sample {
+                    t: f64::from(i),
+                    v: f64::from(i),
+                })
+                .collect();
+        }
+        let mut xy = Xy {
+            persistence: 3,
+            ..Xy::default()
+        };
+        for _ in 0..10 {
+            xy.update(&channels, true);
+        }
+        assert_eq!(xy.history.len(), 4, "the latest and 3 earlier acquisitions");
+        assert!(
+            xy.history.iter().all(|points| points.len() <= MAX_POINTS),
+            "records are thinned"
+        );
+        assert_eq!(xy.history[0][1], (3.0, 3.0), "evenly");
+
+        xy.persistence = 1;
+        xy.update(&channels, false);
+        assert_eq!(xy.history.len(), 2, "fewer kept at once");
+
+        xy.clear();
+        assert!(xy.history.is_empty(), "cleared");
+    }
+}
@@ -0,0 +1,216 @@
+//! Scope input channels.
+
+use std::sync::Arc;
+
+use crate::acquisition::Averager;
+use crate::math::Math;
+use crate::signal::{Generator, Recording, Sample, SignalSource};
+
+/// How the input is coupled to the channel's vertical amplifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Coupling {
+    Dc,
+
+    /// Removes the mean of each acquisition.
+    Ac,
+
+    /// Input grounded: the trace sits at 0 V.
+    Gnd,
+}
+
+impl Coupling {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dc => "DC",
+            Self::Ac => "AC",
+            Self::Gnd => "GND",
+        }
+    }
+
+    /// Applies the coupling to one acquisition in place.
+    pub fn apply(self, samples: &mut [Sample]) {
+        match self {
+            Self::Dc => {}
+            Self::Ac => {
+                if samples.is_empty() {
+                    return;
+                }
+                let mean = samples.iter().map(|s| s.v).sum::<f64>() / samples.len() as f64;
+                for s in samples {
+                    s.v -= mean;
+                }
+            }
+            Self::Gnd => {
+                for s in samples {
+                    s.v = 0.0;
+                }
+            }
+        }
+    }
+}
+
+/// Trace colours, in the order new channels get them.
+pub const CHANNEL_COLORS: [egui::Color32; 4] = [
+    egui::Color32::YELLOW,
+    egui::Color32::from_rgb(0, 220, 220),
+    egui::Color32::from_rgb(230, 60, 230),
+    egui::Color32::from_rgb(70, 130, 255),
+];
+
+/// Math channel trace colours, in the order new math channels get them.
+pub const MATH_COLORS: [egui::Color32; 3] = [
+    egui::Color32::from_rgb(255, 140, 40),
+    egui::Color32::from_rgb(120, 230, 90),
+    egui::Color32::from_rgb(255, 90, 110),
+];
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Channel {
+    pub name: String,
+    pub enabled: bool,
+    pub source: Generator,
+
+    /// A loaded recording, used instead of `source` while present.
+    #[serde(skip)]
+    pub recording: Option<Arc<Recording>>,
+
+    /// Makes this a math channel: the trace is computed from other channels' traces instead
+    /// of sampling `source`.
+    pub math: Option<Math>,
+
+    /// Vertical scale (V/div).
+    pub volts_per_div: f64,
+
+    /// Vertical offset (V), added to the signal before it is drawn.
+    pub offset: f64,
+
+    pub color: egui::Color32,
+    pub coupling: Coupling,
+
+    /// The most recent acquisition.
+    #[serde(skip)]
+    pub trace: Vec<Sample>,
+
+    /// Earlier acquisitions, for averaging.
+    #[serde(skip)]
+    pub average: Averager,
+
+    /// Mean (V) of the most recent record before coupling. An AC-coupled channel's ADC
+    /// range is centred on it.
+    #[serde(skip)]
+    pub dc_level: f64,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Channel {
+    /// Channel number `index + 1`, with default settings.
+    pub fn new(index: usize) -> Self {
+        Self {
+            name: format!("CH{}", index + 1),
+            enabled: true,
+            source: Generator::default(),
+            recording: None,
+            math: None,
+            volts_per_div: 1.0,
+            offset: 0.0,
+            color: CHANNEL_COLORS[index % CHANNEL_COLORS.len()],
+            coupling: Coupling::Dc,
+            trace: Vec::new(),
+            average: Averager::default(),
+            dc_level: 0.0,
+        }
+    }
+
+    /// Math channel `M{number + 1}`.
+    pub fn new_math(number: usize, math: Math) -> Self {
+        Self {
+            name: format!("M{}", number + 1),
+            math: Some(math),
+            color: MATH_COLORS[number % MATH_COLORS.len()],
+            ..Self::new(0)
+        }
+    }
+
+    /// Where the channel's input comes from. Not used for math channels.
+    pub fn signal(&self) -> &dyn SignalSource {
+        match &self.recording {
+            Some(recording) => recording.as_ref(),
+            None => &self.source,
+        }
+    }
+
+    /// Unit of the trace: volts, or for a math channel the unit of its result.
+    pub fn unit(&self) -> &'static str {
+        self.math.map_or("V", |math| math.operator.unit())
+    }
+
+    /// Screen position of the voltage `v`, in divisions above the center line.
+    pub fn to_divisions(&self, v: f64) -> f64 {
+        (v + self.offset) / self.volts_per_div
+    }
+
+    /// Voltage drawn `divisions` above the center line.
+    pub fn volts_at_divisions(&self, divisions: f64) -> f64 {
+        divisions * self.volts_per_div - self.offset
+    }
+}
+
+/// Keeps `source`, a channel index, pointing at the same channel after channel `removed` is
+/// removed. A source on the removed channel moves to the first channel.
+pub fn channel_removed(source: &mut usize, removed: usize) {
+    match (*source).cmp(&removed) {
+        std::cmp::Ordering::Less => {}
+        std::cmp::Ordering::Equal => *source = 0,
+        std::cmp::Ordering::Greater => *source -= 1,
+    }
+}
+
+/// The lowest number `n` whose name, `CH{n + 1}` or for math channels `M{n + 1}`, no
+/// channel has yet, for a new channel.
+pub fn unused_number(channels: &[Channel], math: bool) -> usize {
+    let prefix = if math { "M" } else { "CH" };
+    (0..)
+        .find(|n| {
+            let name = format!("{prefix}{}", n + 1);
+            channels.iter().all(|channel| channel.name != name)
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Channel, channel_removed, unused_number};
+    use crate::math::Math;
+
+    #[test]
+    fn new_channels_take_the_first_free_name() {
+        let mut channels = vec![Channel::new(1), Channel::new_math(0, Math::default())];
+        assert_eq!(unused_number(&channels, false), 0, "CH1 was removed");
+        channels.insert(0, Channel::new(0));
+        assert_eq!(unused_number(&channels, false), 2, "after CH1 and CH2");
+        assert_eq!(
+            unused_number(&channels, true),
+            1,
+            "math channels count apart"
+        );
+    }
+
+    #[test]
+    fn sources_follow_removed_channels() {
+        let mut sources = [0, 1, 2, 3];
+        for source in &mut sources {
+            channel_removed(source, 1);
+        }
+        assert_eq!(
+            sources,
+            [0, 0, 1, 2],
+            "earlier kept, removed to the first, later shifted down"
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Writing acquired traces to CSV and JSON files.
+//!
+//! Every channel in an export shares one time base, so the files have a single time column
+//! and one value column per channel, in the channel's unit. The scope settings go in a
+//! metadata header.
+
+use std::fmt::Write as _;
+
+use crate::channel::Channel;
+use crate::signal::{Sample, WaveformType};
+use crate::trigger::Trigger;
+
+/// Which part of the acquisition to export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum ExportRange {
+    /// Only the samples shown on screen.
+    Visible,
+
+    /// Everything acquired, including samples panned or zoomed out of view.
+    FullRecord,
+}
+
+impl ExportRange {
+    pub const ALL: [Self; 2] = [Self::Visible, Self::FullRecord];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Visible => "Visible",
+            Self::FullRecord => "Full record",
+        }
+    }
+}
+
+/// One channel's samples with the settings they were acquired with.
+pub struct ExportChannel<'a> {
+    pub channel: &'a Channel,
+    pub samples: &'a [Sample],
+
+    /// Where the signal comes from, see [`describe_source`].
+    pub source: String,
+}
+
+/// The traces to export.
+pub struct Export<'a> {
+    /// Horizontal scale (s/div).
+    pub secs_per_div: f64,
+    pub trigger: &'a Trigger,
+
+    /// Name of the channel the trigger looks at.
+    pub trigger_source: &'a str,
+
+    pub channels: Vec<ExportChannel<'a>>,
+}
+
+impl Export<'_> {
+    /// Number of rows: the shortest trace, so every row has a value for every channel.
+    pub fn len(&self) -> usize {
+        self.channels
+            .iter()
+            .map(|channel| channel.samples.len())
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sample times (s), taken from the first channel.
+    fn times(&self) -> &[Sample] {
+        self.channels
+            .first()
+            .map_or(&[][..], |channel| &channel.samples[..self.len()])
+    }
+
+    /// Sa/s, or 0 if there are fewer than two samples.
+    pub fn sample_rate(&self) -> f64 {
+        match self.times() {
+            [first, .., last] if last.t > first.t => (self.len() - 1) as f64 / (last.t - first.t),
+            _ => 0.0,
+        }
+    }
+
+    fn trigger_description(&self) -> String {
+        let trigger = self.trigger;
+        format!(
+            "{} {}, {} mode, level {} V, holdoff {} s",
+            self.trigger_source,
+            trigger.slope.name(),
+            trigger.mode.name(),
+            trigger.level,
+            trigger.holdoff
+        )
+    }
+
+    /// CSV with one row per sample time.
+    ///
+    /// The first line is the column header; the metadata follows as `#` comment lines, so the
+    /// file can be loaded back with the default import settings.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        // Writing to a `String` never fails
+        self.write_csv(&mut csv).unwrap_or_default();
+        csv
+    }
+
+    fn write_csv(&self, csv: &mut String) -> std::fmt::Result {
+        csv.push_str("time (s)");
+        for channel in &self.channels {
+            write!(
+                csv,
+                ",{} ({})",
+                channel.channel.name,
+                channel.channel.unit()
+            )?;
+        }
+        csv.push('\n');
+
+        writeln!(csv, "# time unit: s")?;
+        writeln!(csv, "# sample rate: {} Sa/s", self.sample_rate())?;
+        writeln!(csv, "# samples: {}", self.len())?;
+        writeln!(csv, "# time/div: {} s", self.secs_per_div)?;
+        writeln!(csv, "# trigger: {}", self.trigger_description())?;
+        for channel in &self.channels {
+            let c = channel.channel;
+            writeln!(
+                csv,
+                "# {}: {}; {} {unit}/div, offset {} {unit}, {} coupling",
+                c.name,
+                channel.source,
+                c.volts_per_div,
+                c.offset,
+                c.coupling.name(),
+                unit = c.unit()
+            )?;
+        }
+
+        for (row, sample) in self.times().iter().enumerate() {
+            write!(csv, "{}", sample.t)?;
+            for channel in &self.channels {
+                write!(csv, ",{}", channel.samples[row].v)?;
+            }
+            csv.push('\n');
+        }
+        Ok(())
+    }
+
+    /// Pretty-printed JSON with the metadata, a `time` array and a `volts` array per channel,
+    /// in the channel's `voltage_unit`.
+    pub fn to_json(&self) -> String {
+        let len = self.len();
+        let json = JsonExport {
+            time_unit: "s",
+            sample_rate: self.sample_rate(),
+            samples: len,
+            secs_per_div: self.secs_per_div,
+            trigger: JsonTrigger {
+                source: self.trigger_source,
+                slope: self.trigger.slope.name(),
+                mode: self.trigger.mode.name(),
+                level: self.trigger.level,
+                holdoff: self.trigger.holdoff,
+            },
+            time: self.times().iter().map(|s| s.t).collect(),
+            channels: self
+                .channels
+                .iter()
+                .map(|channel| {
+                    let c = channel.channel;
+                    JsonChannel {
+                        name: &c.name,
+                        source: &channel.source,
+                        voltage_unit: c.unit(),
+                        volts_per_div: c.volts_per_div,
+                        offset: c.offset,
+                        coupling: c.coupling.name(),
+                        volts: channel.samples[..len].iter().map(|s| s.v).collect(),
+                    }
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&json).unwrap_or_default()
+    }
+}
+
+#[derive(serde::Serialize)]
+struct JsonExport<'a> {
+    time_unit: &'static str,
+    sample_rate: f64,
+    samples: usize,
+    secs_per_div: f64,
+    trigger: JsonTrigger<'a>,
+    time: Vec<f64>,
+    channels: Vec<JsonChannel<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct JsonTrigger<'a> {
+    source: &'a str,
+    slope: &'static str,
+    mode: &'static str,
+    level: f64,
+    holdoff: f64,
+}
+
+#[derive(serde::Serialize)]
+struct JsonChannel<'a> {
+    name: &'a str,
+    source: &'a str,
+    voltage_unit: &'static str,
+    volts_per_div: f64,
+    offset: f64,
+    coupling: &'static str,
+    volts: Vec<f64>,
+}
+
+/// A one-line summary of where a channel's signal comes from. Math channels name their
+/// operands from `channels`.
+pub fn describe_source(channel: &Channel, channels: &[Channel]) -> String {
+    if let Some(math) = &channel.math {
+        let name = |index: usize| channels.get(index).map_or("?", |c| c.name.as_str());
+        return format!("math {}", math.describe(name(math.a), name(math.b)));
+    }
+    if let Some(recording) = &channel.recording {
+        return format!("recording {}", recording.name);
+    }
+    let g = &channel.source;
+    let shape = match g.waveform_type {
+        WaveformType::WhiteNoise | WaveformType::PinkNoise => {
+            format!(
+                "{}, {} V rms, seed {}",
+                g.waveform_type.name(),
+                g.amplitude,
+                g.seed
+            )
+        }
+        WaveformType::Expression => format!(
+            "expression \"{}\", f = {} Hz, A = {} V",
+            g.expression.text(),
+            g.freq,
+            g.amplitude
+        ),
+        WaveformType::Pulse => format!(
+            "Pulse, {} Hz, amplitude {} V, duty {} %",
+            g.freq,
+            g.amplitude,
+            g.duty * 100.0
+        ),
+        WaveformType::Dc => format!("DC, {} V", g.amplitude),
+        shape => format!(
+            "{}, {} Hz, amplitude {} V",
+            shape.name(),
+            g.freq,
+            g.amplitude
+        ),
+    };
+    format!(
+        "{shape}, DC offset {} V, phase {}°",
+        g.offset_volts, g.phase_deg
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Export, ExportChannel, describe_source};
+    use crate::channel::Channel;
+    use crate::math::{Math, Operator};
+    use crate::signal::csv::{CsvOptions, parse_csv};
+    use crate::signal::{Generator, Sample, WaveformType};
+    use crate::trigger::Trigger;
+
+    /// CH1, a square wave on CH2 and their product on M1.
+    fn channels() -> [Channel; 3] {
+        let mut square = Channel::new(1);
+        square.source = Generator {
+            waveform_type: WaveformType::Square,
+            freq: 1000.0,
+            amplitude: 2.0,
+            ..Default::default()
+        };
+        let product = Math {
+            operator: Operator::Multiply,
+            ..Math::default()
+        };
+        [Channel::new(0), square, Channel::new_math(0, product)]
+    }
+
+    fn traces(channels: &[Channel]) -> Vec<Vec<Sample>> {
+        let mut traces: Vec<Vec<Sample>> = Vec::new();
+        for channel in channels {
+            let trace = match channel.math {
+                Some(math) => math.apply(&traces[math.a], &traces[math.b]),
+                None => channel.signal().samples(-5e-3, 5e-3, 101),
+            };
+            traces.push(trace);
+        }
+        traces
+    }
+
+    fn export<'a>(
+        trigger: &'a Trigger,
+        channels: &'a [Channel],
+        traces: &'a [Vec<Sample>],
+    ) -> Export<'a> {
+        Export {
+            secs_per_div: 1e-3,
+            trigger,
+            trigger_source: "CH1",
+            channels: channels
+                .iter()
+                .zip(traces)
+                .map(|(channel, samples)| ExportChannel {
+                    channel,
+                    samples,
+                    source: describe_source(channel, channels),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn csv_loads_back_with_default_import_settings() {
+        let trigger = Trigger::default();
+        let channels = channels();
+        let traces = traces(&channels);
+        let export = export(&trigger, &channels, &traces);
+        assert_eq!(export.len(), 101, "rows");
+        assert!(
+            (export.sample_rate() - 10_000.0).abs() < 1e-6,
+            "sample rate"
+        );
+
+        let csv = export.to_csv();
+        assert!(
+            csv.starts_with("time (s),CH1 (V),CH2 (V),M1 (V²)\n"),
+            "{csv}"
+        );
+        assert!(csv.contains("# sample rate: "), "{csv}");
+        assert!(
+            csv.contains("# CH2: Square, 1000 Hz, amplitude 2 V"),
+            "{csv}"
+        );
+        assert!(csv.contains("; 1 V²/div, offset 0 V²,"), "{csv}");
+
+        for (column, trace) in traces.iter().enumerate() {
+            let options = CsvOptions {
+                voltage_column: column + 1,
+                ..Default::default()
+            };
+            let loaded = parse_csv(&csv, &options);
+            assert_eq!(loaded.as_ref(), Ok(trace), "column {column}");
+        }
+    }
+
+    #[test]
+    fn json_has_metadata_and_columns() {
+        let trigger = Trigger::default();
+        let channels = channels();
+        let traces = traces(&channels);
+        let json = export(&trigger, &channels, &traces).to_json();
+
+        let value: serde_json::Value = serde_json::from_str(&json).expect("valid JSON");
+        assert_eq!(value["time_unit"], "s", "{json}");
+        assert_eq!(value["samples"], 101, "{json}");
+        assert_eq!(value["trigger"]["source"], "CH1", "{json}");
+        assert_eq!(value["time"].as_array().map(Vec::len), Some(101), "{json}");
+        assert_eq!(value["channels"][1]["name"], "CH2", "{json}");
+        assert_eq!(value["channels"][1]["volts"][0], traces[1][0].v, "{json}");
+        assert_eq!(value["channels"][1]["voltage_unit"], "V", "{json}");
+        assert_eq!(value["channels"][2]["voltage_unit"], "V²", "{json}");
+        assert_eq!(value["channels"][2]["volts"][0], traces[2][0].v, "{json}");
+    }
+
+    #[test]
+    fn rows_limited_to_shortest_trace() {
+        let trigger = Trigger::default();
+        let channels = channels();
+        let mut traces = traces(&channels);
+        traces[1].truncate(10);
+        let export = export(&trigger, &channels, &traces);
+        assert_eq!(export.len(), 10, "rows");
+        assert_eq!(
+            export
+                .to_csv()
+                .lines()
+                .filter(|l| !l.starts_with('#'))
+                .count(),
+            11,
+            "lines"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Display interpolation: how a trace is drawn between its samples.
+//!
+//! Sin(x)/x interpolation reconstructs the band-limited signal through the samples, which is
+//! exact for a signal with nothing at or above half the sample rate. The kernel is cut off
+//! with a Lanczos window after [`TAPS`] samples each side, and the record is extended with
+//! its end samples, so it is slightly off near the ends of the record.
+
+use std::f64::consts::PI;
+
+use crate::signal::Sample;
+
+/// Samples each side of a point that sin(x)/x interpolation uses.
+pub const TAPS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Interpolation {
+    /// Dots at the samples.
+    None,
+
+    /// Straight lines between the samples.
+    Linear,
+
+    /// The band-limited signal through the samples.
+    Sinc,
+}
+
+impl Interpolation {
+    pub const ALL: [Self; 3] = [Self::None, Self::Linear, Self::Sinc];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None (dots)",
+            Self::Linear => "Linear",
+            Self::Sinc => "Sin(x)/x",
+        }
+    }
+}
+
+/// Value at `t` of the signal through the evenly spaced `samples`, by sin(x)/x
+/// interpolation.
+pub fn sinc_at(samples: &[Sample], t: f64) -> f64 {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return 0.0;
+    };
+    if samples.len() < 2 {
+        return first.v;
+    }
+    let dt = (last.t - first.t) / (samples.len() - 1) as f64;
+    let position = (t - first.t) / dt;
+    let nearest = position.round();
+    if (position - nearest).abs() < 1e-9 && (0.0..samples.len() as f64).contains(&nearest) {
+        return samples[nearest as usize].v;
+    }
+
+    let last_index = samples.len() as isize - 1;
+    let start = position.floor() as isize - TAPS as isize + 1;
+    (start..start + 2 * TAPS as isize)
+        .map(|k| samples[k.clamp(0, last_index) as usize].v * lanczos(position - k as f64))
+        .sum()
+}
+
+/// `count` evenly spaced points from `t_start` to `t_end` of the signal through the evenly
+/// spaced `samples`, by sin(x)/x interpolation.
+pub fn sinc(samples: &[Sample], t_start: f64, t_end: f64, count: usize) -> Vec<Sample> {
+    let dt = if count > 1 {
+        (t_end - t_start) / (count - 1) as f64
+    } else {
+        0.0
+    };
+    (0..count)
+        .map(|i| {
+            let t = t_start + i as f64 * dt;
+            Sample {
+                t,
+                v: sinc_at(samples, t),
+            }
+        })
+        .collect()
+}
+
+/// Sin(x)/x kernel, `x` in sample periods, with a Lanczos window [`TAPS`] wide.
+fn lanczos(x: f64) -> f64 {
+    let normalized_sinc = |x: f64| {
+        if x == 0.0 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        }
+    };
+    if x.abs() >= TAPS as f64 {
+        0.0
+    } else {
+        normalized_sinc(x) * normalized_sinc(x / TAPS as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sinc, sinc_at};
+    use crate::signal::{Generator, SignalSource as _, WaveformType};
+
+    fn sine() -> Generator {
+        Generator {
+            waveform_type: WaveformType::Sine,
+            freq: 1.0,
+            amplitude: 1.0,
+            phase_deg: 30.0,
+            ..Generator::default()
+        }
+    }
+
+    #[test]
+    fn sinc_reconstructs_a_sparsely_sampled_sine() {
+        let sine = sine();
+        // 5 samples per period over 20 periods, shown 40 times finer
+        let samples = sine.samples(0.0, 20.0, 101);
+        let middle = sinc(&samples, 8.0, 12.0, 801);
+        for s in &middle {
+            let expected = sine.voltage_at(s.t);
+            assert!(
+                (s.v - expected).abs() < 0.01,
+                "at {} s: {} vs {expected}",
+                s.t,
+                s.v
+            );
+        }
+
+        let whole = sinc(&samples, 0.0, 20.0, 4001);
+        let worst = whole
+            .iter()
+            .map(|s| (s.v - sine.voltage_at(s.t)).abs())
+            .fold(0.0, f64::max);
+        assert!(
+            worst < 0.2,
+            "end effects stay small over the whole record: {worst}"
+        );
+    }
+
+    #[test]
+    fn sinc_passes_through_the_samples() {
+        let samples = sine().samples(0.0, 2.0, 9);
+        for s in &samples {
+            assert_eq!(sinc_at(&samples, s.t), s.v, "at {} s", s.t);
+        }
+        assert_eq!(sinc_at(&samples[..1], 1.0), samples[0].v, "single sample");
+        assert_eq!(sinc_at(&[], 1.0), 0.0, "no samples");
+    }
+}
@@ -1,4 +1,13 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod acquisition;
 mod app;
+pub mod channel;
+pub mod export;
+pub mod interpolation;
+pub mod math;
+pub mod measure;
+pub mod signal;
+pub mod spectrum;
+pub mod trigger;
 pub use app::TemplateApp;
@@ -0,0 +1,290 @@
+//! Math channels: traces computed from other channels' traces.
+
+use crate::channel::channel_removed;
+use crate::signal::Sample;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+
+    /// Division by 0 V gives 0.
+    Divide,
+
+    Invert,
+
+    /// Rate of change, from central differences.
+    Derivative,
+
+    /// Running integral from the start of the record.
+    Integral,
+
+    Abs,
+
+    /// `gain · A + offset`.
+    ScaleOffset,
+}
+
+impl Operator {
+    pub const ALL: [Self; 9] = [
+        Self::Add,
+        Self::Subtract,
+        Self::Multiply,
+        Self::Divide,
+        Self::Invert,
+        Self::Derivative,
+        Self::Integral,
+        Self::Abs,
+        Self::ScaleOffset,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Add => "A + B",
+            Self::Subtract => "A − B",
+            Self::Multiply => "A × B",
+            Self::Divide => "A ÷ B",
+            Self::Invert => "−A",
+            Self::Derivative => "dA/dt",
+            Self::Integral => "∫A dt",
+            Self::Abs => "|A|",
+            Self::ScaleOffset => "k·A + c",
+        }
+    }
+
+    /// Whether the operator takes a second operand.
+    pub fn is_binary(self) -> bool {
+        matches!(
+            self,
+            Self::Add | Self::Subtract | Self::Multiply | Self::Divide
+        )
+    }
+
+    /// Unit of the result, for operands in volts.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Self::Multiply => "V²",
+            Self::Divide => "V/V",
+            Self::Derivative => "V/s",
+            Self::Integral => "V·s",
+            Self::Add | Self::Subtract | Self::Invert | Self::Abs | Self::ScaleOffset => "V",
+        }
+    }
+}
+
+/// What a math channel computes, and from which channels.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Math {
+    pub operator: Operator,
+
+    /// Index of the first operand's channel.
+    pub a: usize,
+
+    /// Index of the second operand's channel, for binary operators.
+    pub b: usize,
+
+    /// `k` of [`Operator::ScaleOffset`].
+    pub gain: f64,
+
+    /// `c` (V) of [`Operator::ScaleOffset`].
+    pub offset: f64,
+}
+
+impl Default for Math {
+    fn default() -> Self {
+        Self {
+            operator: Operator::Subtract,
+            a: 0,
+            b: 1,
+            gain: 1.0,
+            offset: 0.0,
+        }
+    }
+}
+
+impl Math {
+    /// The operation written out with the operands' names, e.g. `CH1 − CH2`.
+    pub fn describe(&self, a: &str, b: &str) -> String {
+        match self.operator {
+            Operator::Add => format!("{a} + {b}"),
+            Operator::Subtract => format!("{a} − {b}"),
+            Operator::Multiply => format!("{a} × {b}"),
+            Operator::Divide => format!("{a} ÷ {b}"),
+            Operator::Invert => format!("−{a}"),
+            Operator::Derivative => format!("d{a}/dt"),
+            Operator::Integral => format!("∫{a} dt"),
+            Operator::Abs => format!("|{a}|"),
+            Operator::ScaleOffset => format!("{}·{a} + {}", self.gain, self.offset),
+        }
+    }
+
+    /// The trace computed from the operand traces `a` and `b`.
+    ///
+    /// All channels are acquired on the same time base, so binary operators pair samples by
+    /// index; the result is as long as the shorter operand.
+    pub fn apply(&self, a: &[Sample], b: &[Sample]) -> Vec<Sample> {
+        let binary = |f: fn(f64, f64) -> f64| {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| Sample {
+                    t: a.t,
+                    v: f(a.v, b.v),
+                })
+                .collect()
+        };
+        let unary =
+            |f: &dyn Fn(f64) -> f64| a.iter().map(|s| Sample { t: s.t, v: f(s.v) }).collect();
+        match self.operator {
+            Operator::Add => binary(|a, b| a + b),
+            Operator::Subtract => binary(|a, b| a - b),
+            Operator::Multiply => binary(|a, b| a * b),
+            Operator::Divide => binary(|a, b| if b == 0.0 { 0.0 } else { a / b }),
+            Operator::Invert => unary(&|v| -v),
+            Operator::Abs => unary(&f64::abs),
+            Operator::ScaleOffset => unary(&|v| self.gain * v + self.offset),
+            Operator::Derivative => derivative(a),
+            Operator::Integral => integral(a),
+        }
+    }
+
+    /// Keeps the operands pointing at the same channels after channel `index` is removed.
+    /// Operands on the removed channel move to the first channel.
+    pub fn channel_removed(&mut self, index: usize) {
+        for operand in [&mut self.a, &mut self.b] {
+            channel_removed(operand, index);
+        }
+    }
+}
+
+/// Central differences, one-sided at the ends.
+fn derivative(samples: &[Sample]) -> Vec<Sample> {
+    if samples.len() < 2 {
+        return samples.iter().map(|s| Sample { t: s.t, v: 0.0 }).collect();
+    }
+    let last = samples.len() - 1;
+    (0..=last)
+        .map(|i| {
+            let (before, after) = (samples[i.saturating_sub(1)], samples[(i + 1).min(last)]);
+            Sample {
+                t: samples[i].t,
+                v: (after.v - before.v) / (after.t - before.t),
+            }
+        })
+        .collect()
+}
+
+/// Trapezoidal running integral, 0 at the first sample.
+fn integral(samples: &[Sample]) -> Vec<Sample> {
+    let mut sum = 0.0;
+    let mut previous: Option<Sample> = None;
+    samples
+        .iter()
+        .map(|&s| {
+            if let Some(p) = previous {
+                sum += (s.t - p.t) * (s.v + p.v) / 2.0;
+            }
+            previous = Some(s);
+            Sample { t: s.t, v: sum }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Math, Operator};
+    use crate::signal::Sample;
+    use std::f64::consts::TAU;
+
+    /// 1001 samples of `f(t)` over one second.
+    fn trace(f: impl Fn(f64) -> f64) -> Vec<Sample> {
+        (0..=1000)
+            .map(|i| {
+                let t = i as f64 / 1000.0;
+                Sample { t, v: f(t) }
+            })
+            .collect()
+    }
+
+    fn apply(operator: Operator, a: &[Sample], b: &[Sample]) -> Vec<Sample> {
+        Math {
+            operator,
+            gain: 2.0,
+            offset: -1.0,
+            ..Math::default()
+        }
+        .apply(a, b)
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = [Sample { t: 0.0, v: 3.0 }, Sample { t: 1.0, v: -2.0 }];
+        let b = [Sample { t: 0.0, v: 2.0 }, Sample { t: 1.0, v: 0.0 }];
+        for (operator, expected) in [
+            (Operator::Add, [5.0, -2.0]),
+            (Operator::Subtract, [1.0, -2.0]),
+            (Operator::Multiply, [6.0, 0.0]),
+            (Operator::Divide, [1.5, 0.0]),
+            (Operator::Invert, [-3.0, 2.0]),
+            (Operator::Abs, [3.0, 2.0]),
+            (Operator::ScaleOffset, [5.0, -5.0]),
+        ] {
+            let result = apply(operator, &a, &b);
+            let volts: Vec<f64> = result.iter().map(|s| s.v).collect();
+            assert_eq!(volts, expected, "{}", operator.name());
+            assert_eq!(result[1].t, 1.0, "{} keeps the time base", operator.name());
+        }
+        assert_eq!(
+            apply(Operator::Add, &a, &b[..1]).len(),
+            1,
+            "binary results are as long as the shorter operand"
+        );
+    }
+
+    #[test]
+    fn derivative_and_integral_of_a_sine() {
+        let sine = trace(|t| (TAU * t).sin());
+
+        let derivative = apply(Operator::Derivative, &sine, &[]);
+        for s in &derivative {
+            let expected = TAU * (TAU * s.t).cos();
+            // One-sided differences at the ends are first order
+            let tolerance = if s.t == 0.0 || s.t == 1.0 { 0.02 } else { 1e-3 };
+            assert!(
+                (s.v - expected).abs() < tolerance,
+                "d/dt at {}: {} vs {expected}",
+                s.t,
+                s.v
+            );
+        }
+
+        let integral = apply(Operator::Integral, &sine, &[]);
+        for s in &integral {
+            let expected = (1.0 - (TAU * s.t).cos()) / TAU;
+            assert!(
+                (s.v - expected).abs() < 1e-5,
+                "∫ at {}: {} vs {expected}",
+                s.t,
+                s.v
+            );
+        }
+    }
+
+    #[test]
+    fn operands_follow_removed_channels() {
+        let mut math = Math {
+            a: 1,
+            b: 3,
+            ..Math::default()
+        };
+        math.channel_removed(2);
+        assert_eq!((math.a, math.b), (1, 2), "later channel shifts down");
+        math.channel_removed(1);
+        assert_eq!(
+            (math.a, math.b),
+            (0, 1),
+            "removed operand falls back to the first"
+        );
+    }
+}
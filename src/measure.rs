@@ -0,0 +1,704 @@
+//! Automatic measurements over an acquisition.
+//!
+//! Amplitude measurements use every sample. The top and base levels of pulses are the most
+//! common values in the upper and lower half of the signal's range, found with a histogram,
+//! so overshoot and ringing do not move them. Timing measurements look for edges crossing the
+//! middle between base and top, with some hysteresis so noise on a slow edge does not count
+//! as several edges.
+
+use crate::signal::Sample;
+
+/// Hysteresis around the mid level used to find edges, as a fraction of the amplitude.
+const HYSTERESIS: f64 = 0.05;
+
+/// Number of histogram bins used to find the top and base levels.
+const HISTOGRAM_BINS: usize = 100;
+
+/// Reference levels for rise and fall times.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum EdgeThresholds {
+    TenNinety,
+    TwentyEighty,
+}
+
+impl EdgeThresholds {
+    pub const ALL: [Self; 2] = [Self::TenNinety, Self::TwentyEighty];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TenNinety => "10–90 %",
+            Self::TwentyEighty => "20–80 %",
+        }
+    }
+
+    /// Lower and upper reference levels, as fractions of the amplitude above the base.
+    pub fn fractions(self) -> (f64, f64) {
+        match self {
+            Self::TenNinety => (0.1, 0.9),
+            Self::TwentyEighty => (0.2, 0.8),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Measurement {
+    PeakToPeak,
+    Max,
+    Min,
+    Rms,
+    Mean,
+    Frequency,
+    Period,
+    PositiveDuty,
+    NegativeDuty,
+    PositiveWidth,
+    NegativeWidth,
+    Top,
+    Base,
+    RiseTime,
+    FallTime,
+    Overshoot,
+    Preshoot,
+    RisingSlew,
+    FallingSlew,
+}
+
+impl Measurement {
+    pub const ALL: [Self; 19] = [
+        Self::PeakToPeak,
+        Self::Max,
+        Self::Min,
+        Self::Rms,
+        Self::Mean,
+        Self::Frequency,
+        Self::Period,
+        Self::PositiveDuty,
+        Self::NegativeDuty,
+        Self::PositiveWidth,
+        Self::NegativeWidth,
+        Self::Top,
+        Self::Base,
+        Self::RiseTime,
+        Self::FallTime,
+        Self::Overshoot,
+        Self::Preshoot,
+        Self::RisingSlew,
+        Self::FallingSlew,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PeakToPeak => "Vpp",
+            Self::Max => "Vmax",
+            Self::Min => "Vmin",
+            Self::Rms => "Vrms",
+            Self::Mean => "Mean",
+            Self::Frequency => "Freq",
+            Self::Period => "Period",
+            Self::PositiveDuty => "+Duty",
+            Self::NegativeDuty => "−Duty",
+            Self::PositiveWidth => "+Width",
+            Self::NegativeWidth => "−Width",
+            Self::Top => "Top",
+            Self::Base => "Base",
+            Self::RiseTime => "Rise",
+            Self::FallTime => "Fall",
+            Self::Overshoot => "Overshoot",
+            Self::Preshoot => "Preshoot",
+            Self::RisingSlew => "+Slew",
+            Self::FallingSlew => "−Slew",
+        }
+    }
+
+    /// Unit of the measurement of a trace in `trace_unit`, e.g. `V/s` for the slew rate of
+    /// a trace in volts.
+    pub fn unit(self, trace_unit: &str) -> String {
+        match self {
+            Self::PeakToPeak
+            | Self::Max
+            | Self::Min
+            | Self::Rms
+            | Self::Mean
+            | Self::Top
+            | Self::Base => trace_unit.to_owned(),
+            Self::Frequency => "Hz".to_owned(),
+            Self::Period
+            | Self::PositiveWidth
+            | Self::NegativeWidth
+            | Self::RiseTime
+            | Self::FallTime => "s".to_owned(),
+            Self::PositiveDuty | Self::NegativeDuty | Self::Overshoot | Self::Preshoot => {
+                "%".to_owned()
+            }
+            Self::RisingSlew | Self::FallingSlew => format!("{trace_unit}/s"),
+        }
+    }
+
+    /// `value` with its unit, for a trace in `trace_unit`, e.g. "1.250 kHz".
+    pub fn format(self, value: f64, trace_unit: &str) -> String {
+        match self {
+            Self::PositiveDuty | Self::NegativeDuty | Self::Overshoot | Self::Preshoot => {
+                format!("{value:.1} %")
+            }
+            _ => format_si(value, &self.unit(trace_unit)),
+        }
+    }
+}
+
+/// Formats `value` with an SI prefix and four significant digits, e.g. "12.50 mV".
+pub fn format_si(value: f64, unit: &str) -> String {
+    const PREFIXES: [(f64, &str); 9] = [
+        (1e12, "T"),
+        (1e9, "G"),
+        (1e6, "M"),
+        (1e3, "k"),
+        (1.0, ""),
+        (1e-3, "m"),
+        (1e-6, "µ"),
+        (1e-9, "n"),
+        (1e-12, "p"),
+    ];
+    if !value.is_finite() {
+        return format!("{value} {unit}");
+    }
+    if value == 0.0 {
+        return format!("0.000 {unit}");
+    }
+    let magnitude = value.abs();
+    let (scale, prefix) = PREFIXES
+        .iter()
+        .find(|(scale, _)| magnitude >= *scale * 0.999_95)
+        .unwrap_or(&PREFIXES[PREFIXES.len() - 1]);
+    let scaled = value / scale;
+    let decimals = match scaled.abs() {
+        m if m < 9.999_5 => 3,
+        m if m < 99.995 => 2,
+        _ => 1,
+    };
+    format!("{scaled:.decimals$} {prefix}{unit}")
+}
+
+/// Results of measuring one acquisition.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Measurements {
+    /// V
+    pub max: f64,
+
+    /// V
+    pub min: f64,
+
+    /// V
+    pub mean: f64,
+
+    /// V
+    pub rms: f64,
+
+    /// Average time (s) between rising (or, failing that, falling) edges.
+    pub period: Option<f64>,
+
+    /// Average time (s) from a rising edge to the next falling edge.
+    pub positive_width: Option<f64>,
+
+    /// Average time (s) from a falling edge to the next rising edge.
+    pub negative_width: Option<f64>,
+
+    /// Most common level (V) in the upper half of the range.
+    pub top: f64,
+
+    /// Most common level (V) in the lower half of the range.
+    pub base: f64,
+
+    /// Average time (s) rising edges take between the reference levels.
+    pub rise_time: Option<f64>,
+
+    /// Average time (s) falling edges take between the reference levels.
+    pub fall_time: Option<f64>,
+
+    /// Voltage (V) between the reference levels used for rise and fall times.
+    pub reference_span: f64,
+}
+
+impl Measurements {
+    /// Measures `samples`, which must be in time order and evenly spaced, timing edges
+    /// between `thresholds`.
+    ///
+    /// `None` if there are no samples.
+    pub fn of(samples: &[Sample], thresholds: EdgeThresholds) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let (min, max) = samples
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), s| {
+                (min.min(s.v), max.max(s.v))
+            });
+        let mean = samples.iter().map(|s| s.v).sum::<f64>() / n;
+        let rms = (samples.iter().map(|s| s.v * s.v).sum::<f64>() / n).sqrt();
+
+        let (base, top) = top_and_base(samples, min, max);
+        let amplitude = top - base;
+        let edges = Edges::find(samples, (top + base) / 2.0, HYSTERESIS * amplitude);
+
+        let (low, high) = thresholds.fractions();
+        let (low, high) = (base + low * amplitude, base + high * amplitude);
+        let (rise_time, fall_time) = transition_times(samples, low, high);
+
+        Some(Self {
+            max,
+            min,
+            mean,
+            rms,
+            period: average_period(&edges.rising).or_else(|| average_period(&edges.falling)),
+            positive_width: average_width(&edges.rising, &edges.falling),
+            negative_width: average_width(&edges.falling, &edges.rising),
+            top,
+            base,
+            rise_time,
+            fall_time,
+            reference_span: high - low,
+        })
+    }
+
+    /// The value of one measurement, if it could be made.
+    pub fn get(&self, measurement: Measurement) -> Option<f64> {
+        let duty = |width: Option<f64>| Some(width? / self.period? * 100.0);
+        let amplitude = self.top - self.base;
+        let percent_of_amplitude =
+            |volts: f64| (amplitude > 0.0).then(|| volts / amplitude * 100.0);
+        let slew = |time: Option<f64>| time.filter(|&t| t > 0.0).map(|t| self.reference_span / t);
+        match measurement {
+            Measurement::PeakToPeak => Some(self.max - self.min),
+            Measurement::Max => Some(self.max),
+            Measurement::Min => Some(self.min),
+            Measurement::Rms => Some(self.rms),
+            Measurement::Mean => Some(self.mean),
+            Measurement::Frequency => self.period.map(|period| 1.0 / period),
+            Measurement::Period => self.period,
+            Measurement::PositiveDuty => duty(self.positive_width),
+            Measurement::NegativeDuty => duty(self.negative_width),
+            Measurement::PositiveWidth => self.positive_width,
+            Measurement::NegativeWidth => self.negative_width,
+            Measurement::Top => Some(self.top),
+            Measurement::Base => Some(self.base),
+            Measurement::RiseTime => self.rise_time,
+            Measurement::FallTime => self.fall_time,
+            Measurement::Overshoot => percent_of_amplitude(self.max - self.top),
+            Measurement::Preshoot => percent_of_amplitude(self.base - self.min),
+            Measurement::RisingSlew => slew(self.rise_time),
+            Measurement::FallingSlew => slew(self.fall_time),
+        }
+    }
+}
+
+/// Running statistics of one measurement over many acquisitions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Statistics {
+    /// The most recent value.
+    pub current: f64,
+
+    pub min: f64,
+    pub max: f64,
+
+    /// Number of values.
+    pub count: u64,
+
+    mean: f64,
+
+    /// Sum of squared differences from the mean (Welford's algorithm).
+    sum_squares: f64,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Self {
+            current: f64::NAN,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            count: 0,
+            mean: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+}
+
+impl Statistics {
+    pub fn add(&mut self, value: f64) {
+        self.current = value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.sum_squares += delta * (value - self.mean);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (!self.is_empty()).then_some(self.mean)
+    }
+
+    /// Population standard deviation.
+    pub fn std_dev(&self) -> Option<f64> {
+        (!self.is_empty()).then(|| (self.sum_squares / self.count as f64).sqrt())
+    }
+}
+
+/// Running statistics of every measurement of one channel.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeasurementStatistics([Statistics; Measurement::ALL.len()]);
+
+impl MeasurementStatistics {
+    /// Adds the measurements of one acquisition. Measurements that could not be made are
+    /// left out.
+    pub fn add(&mut self, measurements: &Measurements) {
+        for measurement in Measurement::ALL {
+            if let Some(value) = measurements.get(measurement) {
+                self.0[measurement as usize].add(value);
+            }
+        }
+    }
+
+    pub fn get(&self, measurement: Measurement) -> &Statistics {
+        &self.0[measurement as usize]
+    }
+}
+
+/// Times (s) where the signal crosses a level.
+#[derive(Default)]
+struct Edges {
+    rising: Vec<f64>,
+    falling: Vec<f64>,
+}
+
+impl Edges {
+    /// Crossings of `level`, counted only once the signal gets `hysteresis` past it.
+    fn find(samples: &[Sample], level: f64, hysteresis: f64) -> Self {
+        let mut edges = Self::default();
+        if hysteresis <= 0.0 {
+            return edges;
+        }
+        let high = level + hysteresis;
+        let low = level - hysteresis;
+
+        // None until the signal has been clearly above or below the level
+        let mut above = None;
+        let mut last_up = None;
+        let mut last_down = None;
+        for pair in samples.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let crossing = || a.t + (level - a.v) / (b.v - a.v) * (b.t - a.t);
+            if a.v < level && level <= b.v {
+                last_up = Some(crossing());
+            } else if a.v > level && level >= b.v {
+                last_down = Some(crossing());
+            }
+
+            if b.v > high && above != Some(true) {
+                if let (Some(false), Some(t)) = (above, last_up) {
+                    edges.rising.push(t);
+                }
+                above = Some(true);
+            } else if b.v < low && above != Some(false) {
+                if let (Some(true), Some(t)) = (above, last_down) {
+                    edges.falling.push(t);
+                }
+                above = Some(false);
+            }
+        }
+        edges
+    }
+}
+
+/// Base and top levels: the centre of the fullest histogram bin in the lower and upper half of
+/// `min..=max`. Falls back to `min` and `max` when there is no spread.
+fn top_and_base(samples: &[Sample], min: f64, max: f64) -> (f64, f64) {
+    let range = max - min;
+    if !range.is_finite() || range <= 0.0 {
+        return (min, max);
+    }
+    // Count and sum per bin, so a level is the mean of the samples in its bin
+    let mut bins = [(0_usize, 0.0); HISTOGRAM_BINS];
+    for s in samples {
+        let bin = ((s.v - min) / range * HISTOGRAM_BINS as f64) as usize;
+        let bin = &mut bins[bin.min(HISTOGRAM_BINS - 1)];
+        bin.0 += 1;
+        bin.1 += s.v;
+    }
+    let level = |bins: &[(usize, f64)]| {
+        let (count, sum) = bins
+            .iter()
+            .max_by_key(|(count, _)| *count)
+            .copied()
+            .unwrap_or_default();
+        sum / count.max(1) as f64
+    };
+    let (lower, upper) = bins.split_at(HISTOGRAM_BINS / 2);
+    (level(lower), level(upper))
+}
+
+/// Average times (s) rising and falling edges take to cross from one of the reference levels
+/// to the other. A crossing only counts if the signal does not turn back in between.
+fn transition_times(samples: &[Sample], low: f64, high: f64) -> (Option<f64>, Option<f64>) {
+    let crossing = |a: Sample, b: Sample, level: f64| {
+        if (a.v < level) == (b.v < level) {
+            None
+        } else {
+            Some(a.t + (level - a.v) / (b.v - a.v) * (b.t - a.t))
+        }
+    };
+    let mut rises = Vec::new();
+    let mut falls = Vec::new();
+    // Time the signal last left the low (rising) or high (falling) reference level
+    let mut left_low = None;
+    let mut left_high = None;
+    for pair in samples.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        // A fast edge can cross both levels between two samples
+        if b.v > a.v {
+            if let Some(t) = crossing(a, b, low) {
+                left_low = Some(t);
+            }
+            if let Some(t) = crossing(a, b, high) {
+                if let Some(start) = left_low.take() {
+                    rises.push(t - start);
+                }
+                left_high = None;
+            }
+        } else {
+            if let Some(t) = crossing(a, b, high) {
+                left_high = Some(t);
+            }
+            if let Some(t) = crossing(a, b, low) {
+                if let Some(start) = left_high.take() {
+                    falls.push(t - start);
+                }
+                left_low = None;
+            }
+        }
+    }
+    let average =
+        |times: &[f64]| (!times.is_empty()).then(|| times.iter().sum::<f64>() / times.len() as f64);
+    (average(&rises), average(&falls))
+}
+
+fn average_period(edges: &[f64]) -> Option<f64> {
+    match edges {
+        [first, .., last] => Some((last - first) / (edges.len() - 1) as f64),
+        _ => None,
+    }
+}
+
+/// Average time from each `start` edge to the first `end` edge after it.
+fn average_width(starts: &[f64], ends: &[f64]) -> Option<f64> {
+    let widths: Vec<f64> = starts
+        .iter()
+        .filter_map(|&start| {
+            let end = ends[ends.partition_point(|&end| end <= start)..].first()?;
+            Some(end - start)
+        })
+        .collect();
+    (!widths.is_empty()).then(|| widths.iter().sum::<f64>() / widths.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        EdgeThresholds, Measurement, MeasurementStatistics, Measurements, Statistics, format_si,
+    };
+    use crate::signal::Sample;
+    use crate::signal::{Generator, SignalSource as _, WaveformType};
+
+    fn measure(generator: &Generator) -> Measurements {
+        // 5 periods of 250 Hz
+        let samples = generator.samples(0.0, 0.02, 20_001);
+        measure_samples(&samples, EdgeThresholds::TenNinety)
+    }
+
+    fn measure_samples(samples: &[Sample], thresholds: EdgeThresholds) -> Measurements {
+        let Some(measurements) = Measurements::of(samples, thresholds) else {
+            panic!("no measurements");
+        };
+        measurements
+    }
+
+    fn assert_close(measurements: &Measurements, measurement: Measurement, expected: f64) {
+        assert_within(
+            measurements,
+            measurement,
+            expected,
+            1e-3 * expected.abs() + 1e-6,
+        );
+    }
+
+    fn assert_within(
+        measurements: &Measurements,
+        measurement: Measurement,
+        expected: f64,
+        tolerance: f64,
+    ) {
+        let value = measurements.get(measurement);
+        assert!(
+            value.is_some_and(|value| (value - expected).abs() < tolerance),
+            "{}: {value:?}, expected {expected}",
+            measurement.name()
+        );
+    }
+
+    #[test]
+    fn sine() {
+        let m = measure(&Generator::default());
+        assert_close(&m, Measurement::PeakToPeak, 10.0);
+        assert_close(&m, Measurement::Max, 5.0);
+        assert_close(&m, Measurement::Min, -5.0);
+        assert_close(&m, Measurement::Rms, 5.0 / 2f64.sqrt());
+        assert_close(&m, Measurement::Mean, 0.0);
+        assert_close(&m, Measurement::Frequency, 250.0);
+        assert_close(&m, Measurement::PositiveDuty, 50.0);
+    }
+
+    #[test]
+    fn pulse_duty_and_width() {
+        let m = measure(&Generator {
+            waveform_type: WaveformType::Pulse,
+            duty: 0.25,
+            amplitude: 2.5,
+            offset_volts: 2.5,
+            ..Default::default()
+        });
+        assert_close(&m, Measurement::Max, 5.0);
+        assert_close(&m, Measurement::Min, 0.0);
+        assert_close(&m, Measurement::Mean, 1.25);
+        assert_close(&m, Measurement::Period, 4e-3);
+        assert_close(&m, Measurement::PositiveDuty, 25.0);
+        assert_close(&m, Measurement::NegativeDuty, 75.0);
+        assert_close(&m, Measurement::PositiveWidth, 1e-3);
+        assert_close(&m, Measurement::NegativeWidth, 3e-3);
+    }
+
+    #[test]
+    fn no_timing_without_edges() {
+        let m = measure(&Generator {
+            waveform_type: WaveformType::Dc,
+            ..Default::default()
+        });
+        assert_close(&m, Measurement::Mean, 5.0);
+        assert_eq!(m.get(Measurement::Frequency), None, "DC has no frequency");
+        assert_eq!(
+            Measurements::of(&[], EdgeThresholds::TenNinety),
+            None,
+            "nothing to measure"
+        );
+    }
+
+    /// 1 kHz pulse between 0 V and 2 V, 10 µs edges, sampled every 0.1 µs.
+    fn pulse_with_edges(waveform_type: WaveformType) -> Vec<Sample> {
+        let generator = Generator {
+            waveform_type,
+            freq: 1000.0,
+            amplitude: 1.0,
+            offset_volts: 1.0,
+            duty: 0.3,
+            edge_time: 10e-6,
+            ..Default::default()
+        };
+        generator.samples(0.0, 5e-3, 50_001)
+    }
+
+    #[test]
+    fn edge_times_and_slew() {
+        for waveform_type in [WaveformType::Square, WaveformType::Pulse] {
+            let samples = pulse_with_edges(waveform_type);
+
+            // Samples on the edges fall into the histogram bins with the levels
+            let m = measure_samples(&samples, EdgeThresholds::TenNinety);
+            assert_within(&m, Measurement::Top, 2.0, 1e-4);
+            assert_within(&m, Measurement::Base, 0.0, 1e-4);
+            assert_close(&m, Measurement::RiseTime, 8e-6);
+            assert_close(&m, Measurement::FallTime, 8e-6);
+            // 2 V in 10 µs
+            assert_close(&m, Measurement::RisingSlew, 2e5);
+            assert_close(&m, Measurement::FallingSlew, 2e5);
+            assert_within(&m, Measurement::Overshoot, 0.0, 0.01);
+            assert_within(&m, Measurement::Preshoot, 0.0, 0.01);
+
+            let m = measure_samples(&samples, EdgeThresholds::TwentyEighty);
+            assert_close(&m, Measurement::RiseTime, 6e-6);
+            assert_close(&m, Measurement::FallTime, 6e-6);
+            assert_close(&m, Measurement::RisingSlew, 2e5);
+        }
+    }
+
+    #[test]
+    fn overshoot_uses_histogram_levels() {
+        // Ring 0.2 V above the top for 20 µs after each rising edge, and dip 0.1 V below
+        // the base just before it
+        let samples: Vec<Sample> = pulse_with_edges(WaveformType::Pulse)
+            .into_iter()
+            .map(|mut s| {
+                let p = (s.t * 1000.0).rem_euclid(1.0);
+                if (0.005..0.025).contains(&p) {
+                    s.v += 0.2;
+                } else if (0.97..0.99).contains(&p) {
+                    s.v -= 0.1;
+                }
+                s
+            })
+            .collect();
+        let m = measure_samples(&samples, EdgeThresholds::TenNinety);
+        assert_close(&m, Measurement::Max, 2.2);
+        assert_within(&m, Measurement::Top, 2.0, 1e-4);
+        assert_within(&m, Measurement::Base, 0.0, 1e-4);
+        assert_within(&m, Measurement::Overshoot, 10.0, 0.01);
+        assert_within(&m, Measurement::Preshoot, 5.0, 0.01);
+    }
+
+    #[test]
+    fn running_statistics() {
+        let mut stats = Statistics::default();
+        assert_eq!(stats.mean(), None, "no values yet");
+        for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.add(value);
+        }
+        assert_eq!(stats.count, 8, "count");
+        assert_eq!((stats.min, stats.max), (2.0, 9.0), "min, max");
+        assert!((stats.current - 9.0).abs() < 1e-12, "current");
+        assert!(
+            (stats.mean().unwrap_or_default() - 5.0).abs() < 1e-12,
+            "mean"
+        );
+        assert!(
+            (stats.std_dev().unwrap_or_default() - 2.0).abs() < 1e-12,
+            "std dev"
+        );
+    }
+
+    #[test]
+    fn statistics_skip_missing_measurements() {
+        let mut stats = MeasurementStatistics::default();
+        let dc = measure(&Generator {
+            waveform_type: WaveformType::Dc,
+            ..Default::default()
+        });
+        stats.add(&dc);
+        stats.add(&dc);
+        assert_eq!(stats.get(Measurement::Mean).count, 2, "mean measured");
+        assert!(stats.get(Measurement::Frequency).is_empty(), "no frequency");
+    }
+
+    #[test]
+    fn si_prefixes() {
+        assert_eq!(format_si(1250.0, "Hz"), "1.250 kHz");
+        assert_eq!(format_si(-0.0125, "V"), "-12.50 mV");
+        assert_eq!(format_si(2.5e-7, "s"), "250.0 ns");
+        assert_eq!(format_si(0.0, "V"), "0.000 V");
+        assert_eq!(format_si(999.99, "V"), "1.000 kV");
+        assert_eq!(Measurement::PositiveDuty.format(25.0, "V"), "25.0 %");
+        assert_eq!(Measurement::Preshoot.format(2.5, "V²"), "2.5 %");
+        assert_eq!(Measurement::Rms.format(2.0, "V²"), "2.000 V²");
+        assert_eq!(Measurement::RisingSlew.format(3e3, "V·s"), "3.000 kV·s/s");
+    }
+}
@@ -0,0 +1,458 @@
+//! UI-free signal sources.
+//!
+//! Everything in here works in SI units (seconds, volts, hertz) and knows nothing about egui,
+//! so the same waveform math can be driven from tests, a CLI or another frontend.
+
+pub mod csv;
+mod expr;
+mod noise;
+mod recording;
+mod wav;
+
+pub use expr::{Expression, ParseError, Variables};
+pub use recording::Recording;
+pub use wav::WavAudio;
+
+use std::f64::consts::TAU;
+
+/// One point of a trace.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Sample {
+    /// Time in seconds. `t = 0` is the horizontal origin of the display.
+    pub t: f64,
+
+    /// Voltage in volts.
+    pub v: f64,
+}
+
+/// Anything that can produce a voltage trace over a time window.
+pub trait SignalSource {
+    /// Voltage at time `t` (seconds).
+    fn voltage_at(&self, t: f64) -> f64;
+
+    /// `count` evenly spaced samples covering `t_start..=t_end`.
+    fn samples(&self, t_start: f64, t_end: f64, count: usize) -> Vec<Sample> {
+        let dt = if count > 1 {
+            (t_end - t_start) / (count - 1) as f64
+        } else {
+            0.0
+        };
+        (0..count)
+            .map(|i| {
+                let t = t_start + i as f64 * dt;
+                Sample {
+                    t,
+                    v: self.voltage_at(t),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum WaveformType {
+    Sine,
+    Square,
+    Triangle,
+    SawtoothRising,
+    SawtoothFalling,
+
+    /// Rectangular wave with a variable duty cycle.
+    Pulse,
+
+    /// Constant level.
+    Dc,
+
+    WhiteNoise,
+    PinkNoise,
+
+    /// User-entered formula, see [`Expression`].
+    Expression,
+}
+
+impl WaveformType {
+    pub const ALL: [Self; 10] = [
+        Self::Sine,
+        Self::Square,
+        Self::Triangle,
+        Self::SawtoothRising,
+        Self::SawtoothFalling,
+        Self::Pulse,
+        Self::Dc,
+        Self::WhiteNoise,
+        Self::PinkNoise,
+        Self::Expression,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sine => "Sine",
+            Self::Square => "Square",
+            Self::Triangle => "Triangle",
+            Self::SawtoothRising => "Sawtooth (rising)",
+            Self::SawtoothFalling => "Sawtooth (falling)",
+            Self::Pulse => "Pulse",
+            Self::Dc => "DC",
+            Self::WhiteNoise => "White noise",
+            Self::PinkNoise => "Pink noise",
+            Self::Expression => "Expression",
+        }
+    }
+
+    pub fn is_noise(self) -> bool {
+        matches!(self, Self::WhiteNoise | Self::PinkNoise)
+    }
+
+    /// Value of a unit-amplitude periodic waveform at `phase` (radians).
+    ///
+    /// This is the one place waveform shapes are defined. Every periodic shape starts its
+    /// period at `phase = 0`; the odd ones are rising through zero there, like `sin(phase)`.
+    /// `duty` is the fraction of the period a [`Self::Pulse`] is high.
+    ///
+    /// Noise and expressions are not defined by a phase and are always 0 here,
+    /// see [`Generator`].
+    pub fn value(self, phase: f64, duty: f64) -> f64 {
+        // Position within the period, in [0, 1)
+        let p = (phase / TAU).rem_euclid(1.0);
+        match self {
+            Self::Sine => phase.sin(),
+            Self::Square => Self::Pulse.value(phase, 0.5),
+            Self::Triangle => {
+                if p < 0.25 {
+                    4.0 * p
+                } else if p < 0.75 {
+                    2.0 - 4.0 * p
+                } else {
+                    4.0 * p - 4.0
+                }
+            }
+            Self::SawtoothRising => {
+                if p < 0.5 {
+                    2.0 * p
+                } else {
+                    2.0 * p - 2.0
+                }
+            }
+            Self::SawtoothFalling => -Self::SawtoothRising.value(phase, duty),
+            Self::Pulse => {
+                if p < duty {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Self::Dc => 1.0,
+            Self::WhiteNoise | Self::PinkNoise | Self::Expression => 0.0,
+        }
+    }
+
+    /// Like [`Self::value`], but [`Self::Square`] and [`Self::Pulse`] take `edge` (a fraction
+    /// of the period) to ramp linearly between their levels.
+    ///
+    /// The ramps are centred on the ideal edges, so the width at the middle level stays
+    /// `duty` of the period. `edge` is limited so the ramps never overlap.
+    pub fn value_with_edges(self, phase: f64, duty: f64, edge: f64) -> f64 {
+        let duty = match self {
+            Self::Square => 0.5,
+            Self::Pulse => duty,
+            _ => return self.value(phase, duty),
+        };
+        let edge = edge.min(duty).min(1.0 - duty);
+        if edge <= 0.0 {
+            return self.value(phase, duty);
+        }
+        // Position within the period, with the rising edge at 0 and the falling edge at
+        // `duty`, and the middle of the low part at either end
+        let mut p = (phase / TAU).rem_euclid(1.0);
+        if p >= (duty + 1.0) / 2.0 {
+            p -= 1.0;
+        }
+        (2.0 * p / edge)
+            .min(2.0 * (duty - p) / edge)
+            .clamp(-1.0, 1.0)
+    }
+}
+
+/// A function generator.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Generator {
+    pub waveform_type: WaveformType,
+
+    /// Hz
+    pub freq: f64,
+
+    /// Peak voltage (V). For noise this is the RMS voltage, for DC the level.
+    pub amplitude: f64,
+
+    /// Fraction of the period a pulse is high, in `0..=1`.
+    pub duty: f64,
+
+    /// Time (s) the edges of a square wave or pulse take to go from one level to the other.
+    /// 0 gives ideal edges.
+    pub edge_time: f64,
+
+    /// Noise seed. The same seed always gives the same noise.
+    pub seed: u64,
+
+    /// DC offset (V) added to the waveform.
+    pub offset_volts: f64,
+
+    /// Phase shift in degrees. Positive values advance the waveform.
+    pub phase_deg: f64,
+
+    /// Formula used by [`WaveformType::Expression`].
+    pub expression: Expression,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            waveform_type: WaveformType::Sine,
+            freq: 250.0,
+            amplitude: 5.0,
+            duty: 0.5,
+            edge_time: 0.0,
+            seed: 0,
+            offset_volts: 0.0,
+            phase_deg: 0.0,
+            expression: Expression::default(),
+        }
+    }
+}
+
+impl SignalSource for Generator {
+    fn voltage_at(&self, t: f64) -> f64 {
+        let unit = match self.waveform_type {
+            WaveformType::WhiteNoise => noise::white(self.seed, t),
+            WaveformType::PinkNoise => noise::pink(self.seed, t),
+            WaveformType::Expression => {
+                // The phase shift moves `t`, and the expression scales itself by `A`
+                let vars = Variables {
+                    t: t + self.phase_deg / 360.0 / self.freq,
+                    f: self.freq,
+                    a: self.amplitude,
+                };
+                return self.offset_volts + self.expression.eval(&vars);
+            }
+            periodic => periodic.value_with_edges(
+                TAU * self.freq * t + self.phase_deg.to_radians(),
+                self.duty,
+                self.edge_time * self.freq,
+            ),
+        };
+        self.offset_volts + self.amplitude * unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator, SignalSource as _, WaveformType, noise};
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+
+    const EPS: f64 = 1e-12;
+
+    fn assert_golden(waveform_type: WaveformType, golden: &[(f64, f64)]) {
+        for &(phase, expected) in golden {
+            let actual = waveform_type.value(phase, 0.5);
+            assert!(
+                (actual - expected).abs() < EPS,
+                "{waveform_type:?} at phase {phase}: expected {expected}, got {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn sine_golden_values() {
+        assert_golden(
+            WaveformType::Sine,
+            &[
+                (0.0, 0.0),
+                (FRAC_PI_4, 0.5_f64.sqrt()),
+                (FRAC_PI_2, 1.0),
+                (PI + FRAC_PI_2, -1.0),
+                (TAU + FRAC_PI_2, 1.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn square_golden_values() {
+        assert_golden(
+            WaveformType::Square,
+            &[
+                (0.0, 1.0),
+                (FRAC_PI_2, 1.0),
+                (PI, -1.0),
+                (PI + FRAC_PI_2, -1.0),
+                (TAU, 1.0),
+                (-FRAC_PI_2, -1.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn triangle_golden_values() {
+        assert_golden(
+            WaveformType::Triangle,
+            &[
+                (0.0, 0.0),
+                (FRAC_PI_4, 0.5),
+                (FRAC_PI_2, 1.0),
+                (PI, 0.0),
+                (PI + FRAC_PI_4, -0.5),
+                (PI + FRAC_PI_2, -1.0),
+                (TAU, 0.0),
+                (-FRAC_PI_4, -0.5),
+            ],
+        );
+    }
+
+    #[test]
+    fn sawtooth_golden_values() {
+        let golden = [
+            (0.0, 0.0),
+            (FRAC_PI_2, 0.5),
+            (3.0 * FRAC_PI_4, 0.75),
+            (PI, -1.0),
+            (PI + FRAC_PI_2, -0.5),
+            (TAU, 0.0),
+        ];
+        assert_golden(WaveformType::SawtoothRising, &golden);
+        let inverted: Vec<(f64, f64)> = golden.iter().map(|&(p, v)| (p, -v)).collect();
+        assert_golden(WaveformType::SawtoothFalling, &inverted);
+    }
+
+    #[test]
+    fn pulse_golden_values() {
+        for (duty, phase, expected) in [
+            (0.25, 0.0, 1.0),
+            (0.25, FRAC_PI_4, 1.0),
+            (0.25, FRAC_PI_2, -1.0),
+            (0.25, PI, -1.0),
+            (0.75, PI, 1.0),
+            (0.75, PI + FRAC_PI_2, -1.0),
+            (0.0, 0.0, -1.0),
+            (1.0, TAU - 1e-9, 1.0),
+        ] {
+            let actual = WaveformType::Pulse.value(phase, duty);
+            assert!(
+                (actual - expected).abs() < EPS,
+                "duty {duty} at phase {phase}: expected {expected}, got {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn pulse_edges_golden_values() {
+        // Position in the period, expected value; 25 % duty, edges 10 % of the period
+        for (p, expected) in [
+            (0.0, 0.0),
+            (0.025, 0.5),
+            (0.05, 1.0),
+            (0.1, 1.0),
+            (0.25, 0.0),
+            (0.275, -0.5),
+            (0.5, -1.0),
+            (0.975, -0.5),
+        ] {
+            let actual = WaveformType::Pulse.value_with_edges(p * TAU, 0.25, 0.1);
+            assert!(
+                (actual - expected).abs() < EPS,
+                "at {p} of the period: expected {expected}, got {actual}"
+            );
+        }
+        let square = WaveformType::Square.value_with_edges(0.05 * TAU, 0.9, 0.2);
+        assert!((square - 0.5).abs() < EPS, "square: {square}");
+        let ideal = WaveformType::Pulse.value_with_edges(0.1, 0.25, 0.0);
+        assert!((ideal - 1.0).abs() < EPS, "ideal: {ideal}");
+    }
+
+    #[test]
+    fn dc_golden_values() {
+        assert_golden(WaveformType::Dc, &[(0.0, 1.0), (1.0, 1.0), (-3.0, 1.0)]);
+    }
+
+    #[test]
+    fn noise_is_reproducible_with_unit_variance() {
+        for waveform_type in [WaveformType::WhiteNoise, WaveformType::PinkNoise] {
+            let generator = Generator {
+                waveform_type,
+                amplitude: 1.0,
+                seed: 42,
+                ..Default::default()
+            };
+            let count = 100_000;
+            let a = generator.samples(0.0, count as f64 / noise::NOISE_RATE, count);
+            let b = generator.samples(0.0, count as f64 / noise::NOISE_RATE, count);
+            assert_eq!(a, b, "{waveform_type:?} must be reproducible");
+
+            let other_seed = Generator {
+                seed: 7,
+                ..generator
+            };
+            assert_ne!(
+                a,
+                other_seed.samples(0.0, count as f64 / noise::NOISE_RATE, count),
+                "{waveform_type:?} must depend on the seed"
+            );
+
+            // Pink noise has long correlations, so only check white noise statistics tightly.
+            let mean = a.iter().map(|s| s.v).sum::<f64>() / count as f64;
+            let variance = a.iter().map(|s| (s.v - mean).powi(2)).sum::<f64>() / count as f64;
+            if waveform_type == WaveformType::WhiteNoise {
+                assert!(mean.abs() < 0.02, "mean {mean}");
+                assert!((variance - 1.0).abs() < 0.03, "variance {variance}");
+            } else {
+                assert!(variance > 0.1 && variance < 2.0, "variance {variance}");
+            }
+        }
+    }
+
+    #[test]
+    fn generator_scales_by_amplitude_and_frequency() {
+        let generator = Generator {
+            waveform_type: WaveformType::Triangle,
+            freq: 250.0,
+            amplitude: 5.0,
+            ..Default::default()
+        };
+        // A quarter period at 250 Hz is 1 ms
+        assert!((generator.voltage_at(0.001) - 5.0).abs() < EPS, "peak");
+        assert!((generator.voltage_at(0.003) + 5.0).abs() < EPS, "trough");
+
+        let samples = generator.samples(0.0, 0.004, 5);
+        let volts: Vec<f64> = samples.iter().map(|s| s.v).collect();
+        for (actual, expected) in volts.iter().zip([0.0, 5.0, 0.0, -5.0, 0.0]) {
+            assert!((actual - expected).abs() < 1e-9, "{volts:?}");
+        }
+    }
+
+    #[test]
+    fn offset_and_phase_compose_with_amplitude_and_frequency() {
+        // 0-5 V TTL square wave
+        let ttl = Generator {
+            waveform_type: WaveformType::Square,
+            freq: 1000.0,
+            amplitude: 2.5,
+            offset_volts: 2.5,
+            ..Default::default()
+        };
+        assert!((ttl.voltage_at(0.0001) - 5.0).abs() < EPS, "high");
+        assert!((ttl.voltage_at(0.0006) - 0.0).abs() < EPS, "low");
+
+        // 90° ahead of a sine is a cosine
+        let cosine = Generator {
+            waveform_type: WaveformType::Sine,
+            freq: 50.0,
+            amplitude: 2.0,
+            phase_deg: 90.0,
+            ..Default::default()
+        };
+        assert!((cosine.voltage_at(0.0) - 2.0).abs() < EPS, "peak at t = 0");
+        assert!(
+            cosine.voltage_at(0.005).abs() < 1e-9,
+            "zero a quarter period later"
+        );
+    }
+}
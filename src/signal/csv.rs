@@ -0,0 +1,182 @@
+//! Reading `time,voltage` traces from CSV files, as exported by most lab equipment.
+
+use std::fmt;
+
+use super::Sample;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Delimiter {
+    Comma,
+    Semicolon,
+    Tab,
+
+    /// Any run of spaces or tabs.
+    Whitespace,
+}
+
+impl Delimiter {
+    pub const ALL: [Self; 4] = [Self::Comma, Self::Semicolon, Self::Tab, Self::Whitespace];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Comma => "Comma",
+            Self::Semicolon => "Semicolon",
+            Self::Tab => "Tab",
+            Self::Whitespace => "Whitespace",
+        }
+    }
+
+    fn split(self, line: &str) -> Vec<&str> {
+        match self {
+            Self::Comma => line.split(',').collect(),
+            Self::Semicolon => line.split(';').collect(),
+            Self::Tab => line.split('\t').collect(),
+            Self::Whitespace => line.split_whitespace().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum TimeUnit {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl TimeUnit {
+    pub const ALL: [Self; 4] = [
+        Self::Seconds,
+        Self::Milliseconds,
+        Self::Microseconds,
+        Self::Nanoseconds,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Seconds => "s",
+            Self::Milliseconds => "ms",
+            Self::Microseconds => "µs",
+            Self::Nanoseconds => "ns",
+        }
+    }
+
+    /// Length of one unit in seconds.
+    pub fn seconds(self) -> f64 {
+        match self {
+            Self::Seconds => 1.0,
+            Self::Milliseconds => 1e-3,
+            Self::Microseconds => 1e-6,
+            Self::Nanoseconds => 1e-9,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CsvOptions {
+    /// Zero-based index of the time column.
+    pub time_column: usize,
+
+    /// Zero-based index of the voltage column.
+    pub voltage_column: usize,
+
+    /// Number of lines (e.g. headers) to skip at the start of the file.
+    pub skip_lines: usize,
+
+    pub delimiter: Delimiter,
+    pub time_unit: TimeUnit,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            time_column: 0,
+            voltage_column: 1,
+            skip_lines: 1,
+            delimiter: Delimiter::Comma,
+            time_unit: TimeUnit::Seconds,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvError {
+    /// One-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// Parses CSV text into samples, with times converted to seconds.
+///
+/// Blank lines and lines starting with `#` are ignored.
+pub fn parse_csv(text: &str, options: &CsvOptions) -> Result<Vec<Sample>, CsvError> {
+    let mut samples = Vec::new();
+    for (index, line) in text.lines().enumerate().skip(options.skip_lines) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = options.delimiter.split(line);
+        let field = |column: usize| -> Result<f64, CsvError> {
+            let error = |message: String| CsvError {
+                line: index + 1,
+                message,
+            };
+            let field = fields
+                .get(column)
+                .ok_or_else(|| error(format!("no column {}", column + 1)))?;
+            let field = field.trim().trim_matches('"');
+            field
+                .parse()
+                .map_err(|err| error(format!("column {}: '{field}': {err}", column + 1)))
+        };
+        samples.push(Sample {
+            t: field(options.time_column)? * options.time_unit.seconds(),
+            v: field(options.voltage_column)?,
+        });
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CsvOptions, Delimiter, TimeUnit, parse_csv};
+    use crate::signal::Sample;
+
+    #[test]
+    fn parses_columns_units_and_headers() {
+        let text = "Scope export\nindex;time (ms);CH1 (V)\n0;0.0;1.5\n\n# comment\n1;0.5;\"-2\"\n";
+        let options = CsvOptions {
+            time_column: 1,
+            voltage_column: 2,
+            skip_lines: 2,
+            delimiter: Delimiter::Semicolon,
+            time_unit: TimeUnit::Milliseconds,
+        };
+        assert_eq!(
+            parse_csv(text, &options),
+            Ok(vec![
+                Sample { t: 0.0, v: 1.5 },
+                Sample { t: 0.0005, v: -2.0 }
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        let options = CsvOptions::default();
+        let err = parse_csv("t,v\n0,1\n1,x\n", &options).map_err(|err| err.line);
+        assert_eq!(err, Err(3));
+        let err = parse_csv("t,v\n0\n", &options).map_err(|err| err.line);
+        assert_eq!(err, Err(2));
+    }
+}
@@ -0,0 +1,557 @@
+//! Arbitrary waveforms from math expressions such as `A*exp(-t/0.002)*sin(2*pi*f*t)`.
+//!
+//! An expression is parsed once into a tree and then evaluated per sample.
+//!
+//! Grammar, lowest precedence first:
+//!
+//! ```text
+//! expr    = term (("+" | "-") term)*
+//! term    = unary (("*" | "/") unary)*
+//! unary   = ("+" | "-") unary | power
+//! power   = primary ("^" unary)?
+//! primary = number | name | name "(" expr ("," expr)* ")" | "(" expr ")"
+//! ```
+//!
+//! Both the parser and the tree are recursive, so nesting and length are limited to keep
+//! pasted junk from overflowing the stack.
+
+use std::fmt;
+
+/// Deepest nesting of brackets, signs and powers.
+const MAX_DEPTH: usize = 64;
+
+/// Most operands in one expression.
+const MAX_OPERANDS: usize = 1000;
+
+/// Values of the variables an expression can refer to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Variables {
+    /// `t`: time (s)
+    pub t: f64,
+
+    /// `f`: frequency (Hz)
+    pub f: f64,
+
+    /// `A`: amplitude (V)
+    pub a: f64,
+}
+
+/// Where and why an expression failed to parse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the expression text.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.position + 1, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The text of an expression together with its compiled form.
+///
+/// Serializes as just the text, and recompiles on deserialization.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(from = "String", into = "String")]
+pub struct Expression {
+    text: String,
+    compiled: Result<Node, ParseError>,
+}
+
+impl Default for Expression {
+    fn default() -> Self {
+        Self::new("A*sin(2*pi*f*t) + 0.3*A*sin(6*pi*f*t)")
+    }
+}
+
+impl From<String> for Expression {
+    fn from(text: String) -> Self {
+        let compiled = Parser::new(&text).parse();
+        Self { text, compiled }
+    }
+}
+
+impl From<Expression> for String {
+    fn from(expression: Expression) -> Self {
+        expression.text
+    }
+}
+
+impl Expression {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self::from(text.into())
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Why the text failed to parse, if it did.
+    pub fn error(&self) -> Option<&ParseError> {
+        self.compiled.as_ref().err()
+    }
+
+    /// Evaluates the expression. An expression that failed to parse evaluates to 0.
+    pub fn eval(&self, vars: &Variables) -> f64 {
+        self.compiled.as_ref().map_or(0.0, |node| node.eval(vars))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Variable {
+    T,
+    F,
+    A,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Ln,
+    Log10,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sin" => Self::Sin,
+            "cos" => Self::Cos,
+            "tan" => Self::Tan,
+            "asin" => Self::Asin,
+            "acos" => Self::Acos,
+            "atan" => Self::Atan,
+            "sinh" => Self::Sinh,
+            "cosh" => Self::Cosh,
+            "tanh" => Self::Tanh,
+            "exp" => Self::Exp,
+            "ln" => Self::Ln,
+            "log10" => Self::Log10,
+            "sqrt" => Self::Sqrt,
+            "abs" => Self::Abs,
+            "sign" => Self::Sign,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "round" => Self::Round,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Self::Min | Self::Max => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        let x = args[0];
+        match self {
+            Self::Sin => x.sin(),
+            Self::Cos => x.cos(),
+            Self::Tan => x.tan(),
+            Self::Asin => x.asin(),
+            Self::Acos => x.acos(),
+            Self::Atan => x.atan(),
+            Self::Sinh => x.sinh(),
+            Self::Cosh => x.cosh(),
+            Self::Tanh => x.tanh(),
+            Self::Exp => x.exp(),
+            Self::Ln => x.ln(),
+            Self::Log10 => x.log10(),
+            Self::Sqrt => x.sqrt(),
+            Self::Abs => x.abs(),
+            Self::Sign => {
+                if x == 0.0 {
+                    0.0
+                } else {
+                    x.signum()
+                }
+            }
+            Self::Floor => x.floor(),
+            Self::Ceil => x.ceil(),
+            Self::Round => x.round(),
+            Self::Min => x.min(args[1]),
+            Self::Max => x.max(args[1]),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f64),
+    Variable(Variable),
+    Negate(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, vars: &Variables) -> f64 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Variable(Variable::T) => vars.t,
+            Self::Variable(Variable::F) => vars.f,
+            Self::Variable(Variable::A) => vars.a,
+            Self::Negate(node) => -node.eval(vars),
+            Self::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(vars), rhs.eval(vars));
+                match op {
+                    BinaryOp::Add => lhs + rhs,
+                    BinaryOp::Sub => lhs - rhs,
+                    BinaryOp::Mul => lhs * rhs,
+                    BinaryOp::Div => lhs / rhs,
+                    BinaryOp::Pow => lhs.powf(rhs),
+                }
+            }
+            Self::Call(function, args) => {
+                let mut values = [0.0; 2];
+                for (value, arg) in values.iter_mut().zip(args) {
+                    *value = arg.eval(vars);
+                }
+                function.apply(&values[..args.len()])
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+
+    /// Calls to [`Self::unary`] in progress: one per level of nesting.
+    depth: usize,
+
+    /// Calls to [`Self::unary`] so far: one per operand.
+    operands: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            depth: 0,
+            operands: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<Node, ParseError> {
+        let node = self.expr()?;
+        self.skip_whitespace();
+        if let Some(c) = self.peek() {
+            return Err(self.error(format!("unexpected '{c}'")));
+        }
+        Ok(node)
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            position: self.pos,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Consumes `c` (after any whitespace) if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                BinaryOp::Add
+            } else if self.eat('-') {
+                BinaryOp::Sub
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                BinaryOp::Mul
+            } else if self.eat('/') {
+                BinaryOp::Div
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("too deeply nested"));
+        }
+        self.operands += 1;
+        if self.operands > MAX_OPERANDS {
+            return Err(self.error("expression too long"));
+        }
+        self.depth += 1;
+        let node = self.signed();
+        self.depth -= 1;
+        node
+    }
+
+    fn signed(&mut self) -> Result<Node, ParseError> {
+        if self.eat('-') {
+            Ok(Node::Negate(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Node, ParseError> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            // Right associative: 2^3^2 = 2^(3^2)
+            let exponent = self.unary()?;
+            Ok(Node::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => self.name(),
+            Some('(') => {
+                self.pos += 1;
+                let node = self.expr()?;
+                if self.eat(')') {
+                    Ok(node)
+                } else {
+                    Err(self.error("expected ')'"))
+                }
+            }
+            Some(c) => Err(self.error(format!("unexpected '{c}'"))),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        let bytes = self.text.as_bytes();
+        let digits = |pos: &mut usize| {
+            while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+                *pos += 1;
+            }
+        };
+        let mut end = start;
+        digits(&mut end);
+        if bytes.get(end) == Some(&b'.') {
+            end += 1;
+            digits(&mut end);
+        }
+        // Exponent, but only if digits follow.
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let mut exponent = end + 1;
+            if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+                exponent += 1;
+            }
+            if bytes.get(exponent).is_some_and(u8::is_ascii_digit) {
+                end = exponent;
+                digits(&mut end);
+            }
+        }
+        let value = self.text[start..end]
+            .parse()
+            .map_err(|err| self.error(format!("invalid number: {err}")))?;
+        self.pos = end;
+        Ok(Node::Number(value))
+    }
+
+    fn name(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.pos += 1;
+        }
+        let name = &self.text[start..self.pos];
+
+        if let Some(function) = Function::from_name(name) {
+            if !self.eat('(') {
+                return Err(self.error(format!("expected '(' after {name}")));
+            }
+            let mut args = vec![self.expr()?];
+            while self.eat(',') {
+                args.push(self.expr()?);
+            }
+            if !self.eat(')') {
+                return Err(self.error("expected ')'"));
+            }
+            if args.len() != function.arity() {
+                return Err(ParseError {
+                    position: start,
+                    message: format!(
+                        "{name} takes {} argument(s), got {}",
+                        function.arity(),
+                        args.len()
+                    ),
+                });
+            }
+            return Ok(Node::Call(function, args));
+        }
+
+        match name {
+            "t" => Ok(Node::Variable(Variable::T)),
+            "f" => Ok(Node::Variable(Variable::F)),
+            "A" => Ok(Node::Variable(Variable::A)),
+            "pi" => Ok(Node::Number(std::f64::consts::PI)),
+            "e" => Ok(Node::Number(std::f64::consts::E)),
+            _ => Err(ParseError {
+                position: start,
+                message: format!("unknown name '{name}'"),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expression, Variables};
+
+    fn eval(text: &str) -> f64 {
+        let expression = Expression::new(text);
+        assert_eq!(expression.error(), None, "{text}");
+        expression.eval(&Variables {
+            t: 0.25,
+            f: 2.0,
+            a: 3.0,
+        })
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("8 / 4 / 2"), 1.0);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(eval("-2 ^ 2"), -4.0);
+        assert_eq!(eval("2 * -3"), -6.0);
+        assert_eq!(eval("1.5e3 + 2e-1"), 1500.2);
+    }
+
+    #[test]
+    fn variables_constants_and_functions() {
+        assert_eq!(eval("A * f * t"), 1.5);
+        assert!((eval("A*sin(2*pi*f*t)") - 0.0).abs() < 1e-12, "sin(pi) = 0");
+        assert!(
+            (eval("2*e") - 2.0 * std::f64::consts::E).abs() < 1e-12,
+            "2*e"
+        );
+        assert_eq!(eval("max(t, min(f, A))"), 2.0);
+        assert_eq!(eval("sqrt(abs(-16))"), 4.0);
+    }
+
+    #[test]
+    fn errors_are_reported_with_position() {
+        for (text, position) in [
+            ("1 +", 3),
+            ("sin t", 4),
+            ("foo(t)", 0),
+            ("(t", 2),
+            ("t t", 2),
+            ("max(t)", 0),
+        ] {
+            let expression = Expression::new(text);
+            let error = expression.error().map(|err| err.position);
+            assert_eq!(error, Some(position), "{text}");
+            assert_eq!(
+                expression.eval(&Variables {
+                    t: 1.0,
+                    f: 1.0,
+                    a: 1.0
+                }),
+                0.0,
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn runaway_input_is_an_error() {
+        let nested = format!("{}t{}", "(".repeat(100_000), ")".repeat(100_000));
+        let negated = format!("{}t", "-".repeat(100_000));
+        let powers = format!("t{}", "^t".repeat(100_000));
+        let long = format!("t{}", "+t".repeat(100_000));
+        for (text, message) in [
+            (nested, "too deeply nested"),
+            (negated, "too deeply nested"),
+            (powers, "too deeply nested"),
+            (long, "expression too long"),
+        ] {
+            let expression = Expression::new(text);
+            let error = expression.error().map(|err| err.message.as_str());
+            assert_eq!(error, Some(message), "{}…", &expression.text()[..8]);
+        }
+        assert_eq!(
+            Expression::new(format!("{}t{}", "(".repeat(50), ")".repeat(50))).error(),
+            None,
+            "reasonable nesting"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Reproducible Gaussian noise.
+//!
+//! Noise is a pure function of the seed and the time, so a given seed always produces the
+//! same trace no matter how, or how often, it is sampled.
+
+/// Rate (Hz) at which independent noise values are drawn.
+pub const NOISE_RATE: f64 = 1.0e6;
+
+/// Number of octaves summed for pink noise (covers ~15 Hz up to [`NOISE_RATE`]).
+const PINK_OCTAVES: u32 = 16;
+
+/// Unit-variance white Gaussian noise at time `t` (seconds).
+pub fn white(seed: u64, t: f64) -> f64 {
+    gaussian(seed, 0, noise_index(t))
+}
+
+/// Unit-variance pink (1/f) Gaussian noise at time `t` (seconds).
+///
+/// Uses the Voss-McCartney algorithm: octave `k` is a white noise value held for
+/// `2^k` noise samples, and the octaves are summed.
+pub fn pink(seed: u64, t: f64) -> f64 {
+    let index = noise_index(t);
+    let sum: f64 = (0..=PINK_OCTAVES)
+        .map(|octave| gaussian(seed, u64::from(octave) + 1, index >> octave))
+        .sum();
+    sum / f64::from(PINK_OCTAVES + 1).sqrt()
+}
+
+fn noise_index(t: f64) -> u64 {
+    // Negative times wrap around, which is fine: we only need distinct indices.
+    (t * NOISE_RATE).floor() as i64 as u64
+}
+
+/// A standard normal value, determined by `seed`, `stream` and `index` (Box-Muller).
+fn gaussian(seed: u64, stream: u64, index: u64) -> f64 {
+    let key = splitmix64(seed ^ splitmix64(stream ^ splitmix64(index)));
+    let u1 = unit_interval(splitmix64(key));
+    let u2 = unit_interval(splitmix64(key ^ 0x5DEE_CE66_D1CE_5EED));
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+/// Maps random bits to a uniform value in `(0, 1]`.
+fn unit_interval(bits: u64) -> f64 {
+    ((bits >> 11) + 1) as f64 / (1_u64 << 53) as f64
+}
+
+/// The `SplitMix64` mixing function: a cheap, well-distributed 64-bit hash.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
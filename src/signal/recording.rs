@@ -0,0 +1,228 @@
+//! Signals recorded elsewhere, e.g. loaded from a file.
+
+use std::sync::Arc;
+
+use super::{Sample, SignalSource};
+
+/// A captured trace, played back in a loop with linear interpolation between samples.
+///
+/// Cloning shares the samples, so a copy with other settings is cheap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    /// Where the recording came from, e.g. a file name.
+    pub name: String,
+
+    data: Data,
+
+    /// Time (s) after which the recording repeats.
+    period: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Data {
+    /// Sorted by time, at least two samples.
+    Timed(Vec<Sample>),
+
+    /// Evenly spaced samples starting at `t = 0`, at least two of them, in units of
+    /// `full_scale` volts.
+    Uniform {
+        sample_rate: f64,
+        full_scale: f64,
+        samples: Arc<[f32]>,
+    },
+}
+
+impl Recording {
+    /// A recording of samples with explicit times, e.g. from a CSV file.
+    ///
+    /// Returns `None` if there are fewer than two samples with distinct times.
+    pub fn new(name: impl Into<String>, mut samples: Vec<Sample>) -> Option<Self> {
+        samples.retain(|s| s.t.is_finite() && s.v.is_finite());
+        samples.sort_by(|a, b| a.t.total_cmp(&b.t));
+        let first = samples.first()?.t;
+        let last = samples.last()?.t;
+        if samples.len() < 2 || last <= first {
+            return None;
+        }
+        // One extra sample interval so that the loop doesn't repeat the end point
+        let period = (last - first) * samples.len() as f64 / (samples.len() - 1) as f64;
+        Some(Self {
+            name: name.into(),
+            data: Data::Timed(samples),
+            period,
+        })
+    }
+
+    /// A recording of evenly spaced samples, e.g. from an audio file, where a sample of 1 is
+    /// `full_scale` volts.
+    ///
+    /// Returns `None` if there are fewer than two samples or the rate is not positive.
+    pub fn uniform(
+        name: impl Into<String>,
+        sample_rate: f64,
+        full_scale: f64,
+        samples: Vec<f32>,
+    ) -> Option<Self> {
+        if samples.len() < 2 || !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            name: name.into(),
+            period: samples.len() as f64 / sample_rate,
+            data: Data::Uniform {
+                sample_rate,
+                full_scale,
+                samples: samples.into(),
+            },
+        })
+    }
+
+    /// Voltage (V) of a sample of 1, for a recording of evenly spaced samples.
+    pub fn full_scale(&self) -> Option<f64> {
+        match &self.data {
+            Data::Timed(_) => None,
+            Data::Uniform { full_scale, .. } => Some(*full_scale),
+        }
+    }
+
+    /// Sets the voltage (V) of a sample of 1. Recordings with explicit times are in volts
+    /// and stay as they are.
+    pub fn set_full_scale(&mut self, volts: f64) {
+        if let Data::Uniform { full_scale, .. } = &mut self.data {
+            *full_scale = volts;
+        }
+    }
+
+    /// Number of samples.
+    pub fn len(&self) -> usize {
+        match &self.data {
+            Data::Timed(samples) => samples.len(),
+            Data::Uniform { samples, .. } => samples.len(),
+        }
+    }
+
+    /// Always false: a recording has at least two samples.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Length of one loop of the recording (s).
+    pub fn duration(&self) -> f64 {
+        self.period
+    }
+
+    /// Average sample rate (Hz).
+    pub fn sample_rate(&self) -> f64 {
+        self.len() as f64 / self.period
+    }
+}
+
+impl SignalSource for Recording {
+    fn voltage_at(&self, t: f64) -> f64 {
+        match &self.data {
+            Data::Timed(samples) => {
+                let start = samples[0].t;
+                let t = start + (t - start).rem_euclid(self.period);
+
+                // First sample after t; t >= start so this is at least 1
+                let index = samples.partition_point(|s| s.t <= t);
+                let a = samples[index - 1];
+                let b = samples.get(index).copied().unwrap_or(Sample {
+                    // Wrap around to the start of the next loop
+                    t: start + self.period,
+                    v: samples[0].v,
+                });
+                let frac = (t - a.t) / (b.t - a.t);
+                a.v + frac * (b.v - a.v)
+            }
+            Data::Uniform {
+                sample_rate,
+                full_scale,
+                samples,
+            } => {
+                let position = t.rem_euclid(self.period) * sample_rate;
+                let index = (position.floor() as usize).min(samples.len() - 1);
+                let frac = position - index as f64;
+                let a = f64::from(samples[index]);
+                let b = f64::from(samples[(index + 1) % samples.len()]);
+                (a + frac * (b - a)) * full_scale
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recording;
+    use crate::signal::{Sample, SignalSource as _};
+
+    #[test]
+    fn interpolates_and_loops() {
+        let samples = vec![
+            Sample { t: 1.0, v: 0.0 },
+            Sample { t: 2.0, v: 2.0 },
+            Sample { t: 3.0, v: -2.0 },
+        ];
+        let recording = Recording::new("test", samples);
+        let Some(recording) = recording else {
+            panic!("valid recording");
+        };
+        assert_eq!(recording.duration(), 3.0);
+        assert_eq!(recording.voltage_at(1.5), 1.0);
+        assert_eq!(recording.voltage_at(2.5), 0.0);
+        // Between the last sample and the start of the next loop
+        assert_eq!(recording.voltage_at(3.5), -1.0);
+        assert_eq!(recording.voltage_at(4.5), 1.0);
+        assert_eq!(recording.voltage_at(-0.5), 0.0);
+    }
+
+    #[test]
+    fn uniform_recording_uses_sample_rate() {
+        let recording = Recording::uniform("test", 4.0, 1.0, vec![0.0, 1.0, 0.0, -1.0]);
+        let Some(recording) = recording else {
+            panic!("valid recording");
+        };
+        assert_eq!(recording.duration(), 1.0);
+        assert_eq!(recording.voltage_at(0.25), 1.0);
+        assert_eq!(recording.voltage_at(0.125), 0.5);
+        assert_eq!(recording.voltage_at(0.875), -0.5);
+        assert_eq!(recording.voltage_at(1.25), 1.0);
+    }
+
+    #[test]
+    fn full_scale_applies_when_sampling() {
+        let recording = Recording::uniform("test", 4.0, 2.0, vec![0.0, 1.0, 0.0, -0.5]);
+        let Some(mut recording) = recording else {
+            panic!("valid recording");
+        };
+        assert_eq!(recording.full_scale(), Some(2.0));
+        assert_eq!(recording.voltage_at(0.25), 2.0);
+        assert_eq!(recording.voltage_at(0.75), -1.0);
+
+        recording.set_full_scale(0.5);
+        assert_eq!(
+            recording.voltage_at(0.25),
+            0.5,
+            "rescaled without re-import"
+        );
+
+        let timed = Recording::new(
+            "timed",
+            vec![Sample { t: 0.0, v: 1.0 }, Sample { t: 1.0, v: 2.0 }],
+        );
+        let Some(mut timed) = timed else {
+            panic!("valid recording");
+        };
+        timed.set_full_scale(10.0);
+        assert_eq!(timed.full_scale(), None, "timed recordings are in volts");
+        assert_eq!(timed.voltage_at(0.0), 1.0);
+    }
+
+    #[test]
+    fn needs_two_distinct_times() {
+        let single = vec![Sample { t: 0.0, v: 1.0 }];
+        assert_eq!(Recording::new("single", single), None);
+        let same_time = vec![Sample { t: 0.0, v: 1.0 }, Sample { t: 0.0, v: 2.0 }];
+        assert_eq!(Recording::new("same time", same_time), None);
+    }
+}
@@ -0,0 +1,100 @@
+//! Decoding WAV audio files.
+
+/// Decoded audio, with samples normalized so that full scale is ±1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavAudio {
+    /// Hz
+    pub sample_rate: u32,
+
+    pub bits_per_sample: u16,
+    pub is_float: bool,
+
+    /// One buffer per audio channel.
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl WavAudio {
+    /// Decodes 8/16/24/32-bit integer PCM or 32-bit float WAV data.
+    pub fn decode(bytes: &[u8]) -> Result<Self, hound::Error> {
+        let reader = hound::WavReader::new(std::io::Cursor::new(bytes))?;
+        let spec = reader.spec();
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let full_scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 / full_scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        let channel_count = usize::from(spec.channels.max(1));
+        let mut channels =
+            vec![Vec::with_capacity(interleaved.len() / channel_count); channel_count];
+        for frame in interleaved.chunks_exact(channel_count) {
+            for (channel, &sample) in channels.iter_mut().zip(frame) {
+                channel.push(sample);
+            }
+        }
+
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            bits_per_sample: spec.bits_per_sample,
+            is_float: spec.sample_format == hound::SampleFormat::Float,
+            channels,
+        })
+    }
+
+    /// Length in seconds.
+    pub fn duration(&self) -> f64 {
+        self.channels.first().map_or(0, Vec::len) as f64 / f64::from(self.sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WavAudio;
+
+    fn encode<S: hound::Sample + Copy>(spec: hound::WavSpec, samples: &[S]) -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).expect("writer");
+        for &sample in samples {
+            writer.write_sample(sample).expect("write");
+        }
+        writer.finalize().expect("finalize");
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn decodes_integer_pcm() {
+        for bits in [8_u16, 16, 24, 32] {
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate: 8000,
+                bits_per_sample: bits,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let half = 1_i32 << (bits - 2);
+            let bytes = encode(spec, &[0_i32, half, -2 * half]);
+            let audio = WavAudio::decode(&bytes).expect("decode");
+            assert_eq!(audio.sample_rate, 8000);
+            assert_eq!(audio.channels, vec![vec![0.0, 0.5, -1.0]], "{bits} bits");
+        }
+    }
+
+    #[test]
+    fn decodes_multichannel_float() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let bytes = encode(spec, &[0.25_f32, -0.25, 0.5, -0.5]);
+        let audio = WavAudio::decode(&bytes).expect("decode");
+        assert!(audio.is_float);
+        assert_eq!(audio.channels, vec![vec![0.25, 0.5], vec![-0.25, -0.5]]);
+        assert_eq!(audio.duration(), 2.0 / 48_000.0);
+    }
+}
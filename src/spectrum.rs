@@ -0,0 +1,484 @@
+//! Magnitude spectrum of an acquisition.
+//!
+//! The record is windowed and zero-padded to a power of two for a radix-2 FFT. Magnitudes
+//! are RMS volts, corrected for the window's gain, so a sine at the centre of a bin reads
+//! its true RMS value.
+
+use std::f64::consts::{PI, TAU};
+
+use crate::signal::Sample;
+
+/// Window function applied to the record before the FFT.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Window {
+    /// No window: best frequency resolution, worst leakage.
+    Rectangular,
+    Hann,
+    Hamming,
+
+    /// 4-term Blackman-Harris: very low side lobes.
+    BlackmanHarris,
+
+    /// Flat top: accurate amplitudes, wide peaks.
+    FlatTop,
+}
+
+impl Window {
+    pub const ALL: [Self; 5] = [
+        Self::Rectangular,
+        Self::Hann,
+        Self::Hamming,
+        Self::BlackmanHarris,
+        Self::FlatTop,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rectangular => "Rectangular",
+            Self::Hann => "Hann",
+            Self::Hamming => "Hamming",
+            Self::BlackmanHarris => "Blackman-Harris",
+            Self::FlatTop => "Flat top",
+        }
+    }
+
+    /// Cosine-sum coefficients: `w(x) = a0 - a1 cos(x) + a2 cos(2x) - …` over one period.
+    fn coefficients(self) -> &'static [f64] {
+        match self {
+            Self::Rectangular => &[1.0],
+            Self::Hann => &[0.5, 0.5],
+            Self::Hamming => &[0.54, 0.46],
+            Self::BlackmanHarris => &[0.358_75, 0.488_29, 0.141_28, 0.011_68],
+            Self::FlatTop => &[
+                0.215_578_95,
+                0.416_631_58,
+                0.277_263_158,
+                0.083_578_947,
+                0.006_947_368,
+            ],
+        }
+    }
+
+    /// The window's `n` values.
+    pub fn values(self, n: usize) -> Vec<f64> {
+        let coefficients = self.coefficients();
+        (0..n)
+            .map(|i| {
+                let x = TAU * i as f64 / n as f64;
+                coefficients
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| {
+                        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                        sign * a * (k as f64 * x).cos()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Half-width (bins) of the main lobe, where the energy of a sine ends up.
+    fn main_lobe(self) -> usize {
+        match self {
+            Self::Rectangular => 1,
+            Self::Hann | Self::Hamming => 2,
+            Self::BlackmanHarris => 4,
+            Self::FlatTop => 5,
+        }
+    }
+}
+
+/// How magnitudes are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Scale {
+    /// dB relative to 1 V RMS.
+    DbV,
+
+    /// V RMS.
+    Linear,
+}
+
+impl Scale {
+    pub const ALL: [Self; 2] = [Self::DbV, Self::Linear];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DbV => "dBV",
+            Self::Linear => "Linear",
+        }
+    }
+
+    /// `vrms` on this scale.
+    pub fn apply(self, vrms: f64) -> f64 {
+        match self {
+            Self::DbV => 20.0 * vrms.max(1e-12).log10(),
+            Self::Linear => vrms,
+        }
+    }
+}
+
+/// One harmonic of the fundamental.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Harmonic {
+    /// Hz
+    pub frequency: f64,
+
+    /// V RMS
+    pub vrms: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spectrum {
+    /// Frequency step (Hz) between bins.
+    pub bin_width: f64,
+
+    /// RMS voltage of each bin from DC up to the Nyquist frequency.
+    pub vrms: Vec<f64>,
+
+    /// Squared FFT magnitudes, before normalization.
+    power: Vec<f64>,
+
+    /// `N · Σw²`: turns summed `power` over a peak into mean square volts (times 2).
+    power_scale: f64,
+
+    window: Window,
+}
+
+impl Spectrum {
+    /// The spectrum of evenly spaced `samples`. `None` if there are fewer than two.
+    pub fn of(samples: &[Sample], window: Window) -> Option<Self> {
+        Self::padded(samples, window, samples.len())
+    }
+
+    /// Like [`Self::of`], zero-padding the samples to at least `size`, rounded up to a
+    /// power of two.
+    fn padded(samples: &[Sample], window: Window, size: usize) -> Option<Self> {
+        let [first, .., last] = samples else {
+            return None;
+        };
+        let n = samples.len();
+        let sample_rate = (n - 1) as f64 / (last.t - first.t);
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return None;
+        }
+
+        let weights = window.values(n);
+        let size = size.max(n).next_power_of_two();
+        let mut data = vec![(0.0, 0.0); size];
+        for ((slot, sample), w) in data.iter_mut().zip(samples).zip(&weights) {
+            *slot = (sample.v * w, 0.0);
+        }
+        fft(&mut data);
+
+        let gain: f64 = weights.iter().sum();
+        let power: Vec<f64> = data[..=size / 2]
+            .iter()
+            .map(|(re, im)| re * re + im * im)
+            .collect();
+        let vrms = power
+            .iter()
+            .enumerate()
+            .map(|(k, p)| {
+                let amplitude = p.sqrt() / gain;
+                // Everything but DC (and Nyquist) is split between positive and negative
+                // frequencies; a sine of amplitude A has RMS A/√2
+                if k == 0 || k == size / 2 {
+                    amplitude
+                } else {
+                    amplitude * 2.0_f64.sqrt()
+                }
+            })
+            .collect();
+
+        Some(Self {
+            bin_width: sample_rate / size as f64,
+            vrms,
+            power,
+            power_scale: size as f64 * weights.iter().map(|w| w * w).sum::<f64>(),
+            window,
+        })
+    }
+
+    /// Hz
+    pub fn nyquist(&self) -> f64 {
+        (self.vrms.len() - 1) as f64 * self.bin_width
+    }
+
+    /// Bins of the `count` highest local maxima, highest first. DC is not a peak.
+    ///
+    /// A flat top, such as the two equal bins of a tone half way between them, counts as
+    /// one peak at its lowest bin.
+    pub fn peaks(&self, count: usize) -> Vec<usize> {
+        let lobe = self.window.main_lobe();
+        let mut peaks: Vec<usize> = (1..self.vrms.len())
+            .filter(|&k| {
+                let neighbours = k.saturating_sub(lobe)..=(k + lobe).min(self.vrms.len() - 1);
+                neighbours.into_iter().all(|j| match j.cmp(&k) {
+                    std::cmp::Ordering::Less => self.vrms[j] < self.vrms[k],
+                    std::cmp::Ordering::Equal => true,
+                    std::cmp::Ordering::Greater => self.vrms[j] <= self.vrms[k],
+                })
+            })
+            .collect();
+        peaks.sort_by(|&a, &b| self.vrms[b].total_cmp(&self.vrms[a]));
+        peaks.truncate(count);
+        peaks
+    }
+
+    /// The fundamental (the highest peak) and its multiples, `count` in all.
+    ///
+    /// Each level is the total power within a main lobe of the expected frequency, so it does
+    /// not depend on where the frequency falls between bins.
+    pub fn harmonics(&self, count: usize) -> Vec<Harmonic> {
+        let Some(&fundamental) = self.peaks(1).first() else {
+            return Vec::new();
+        };
+        // Refine the fundamental to the centre of mass of its peak
+        let lobe = self.window.main_lobe();
+        let bins = fundamental.saturating_sub(lobe)..=(fundamental + lobe);
+        let (moment, total) = bins
+            .filter_map(|k| Some((k, *self.power.get(k)?)))
+            .fold((0.0, 0.0), |(m, t), (k, p)| (m + k as f64 * p, t + p));
+        let fundamental = moment / total;
+
+        (1..=count)
+            .map(|n| n as f64 * fundamental)
+            .take_while(|&bin| bin.round() < self.power.len() as f64)
+            .map(|bin| {
+                let centre = bin.round() as usize;
+                let lobe = centre.saturating_sub(lobe).max(1)..=(centre + lobe);
+                let power: f64 = lobe.filter_map(|k| self.power.get(k)).sum();
+                Harmonic {
+                    frequency: bin * self.bin_width,
+                    vrms: (2.0 * power / self.power_scale).sqrt(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Total harmonic distortion: RMS of the harmonics relative to the fundamental, as a
+/// fraction. `None` without a fundamental.
+pub fn thd(harmonics: &[Harmonic]) -> Option<f64> {
+    let (fundamental, rest) = harmonics.split_first()?;
+    let sum: f64 = rest.iter().map(|h| h.vrms * h.vrms).sum();
+    (fundamental.vrms > 0.0).then(|| sum.sqrt() / fundamental.vrms)
+}
+
+/// Short-time spectra of `samples`, oldest first.
+///
+/// There is one spectrum per frame of `size` samples, with successive frames overlapping by
+/// the fraction `overlap`. A record shorter than a frame gives a single zero-padded spectrum,
+/// so every spectrum has the same bins.
+pub fn spectrogram(samples: &[Sample], size: usize, overlap: f64, window: Window) -> Vec<Spectrum> {
+    if samples.len() <= size {
+        return Spectrum::padded(samples, window, size)
+            .into_iter()
+            .collect();
+    }
+    let hop = ((size as f64 * (1.0 - overlap.clamp(0.0, 1.0))).round() as usize).max(1);
+    (0..=samples.len() - size)
+        .step_by(hop)
+        .filter_map(|start| Spectrum::padded(&samples[start..start + size], window, size))
+        .collect()
+}
+
+/// In-place radix-2 FFT of complex `(re, im)` values. The length must be a power of two.
+fn fft(data: &mut [(f64, f64)]) {
+    let n = data.len();
+    debug_assert!(n.is_power_of_two(), "FFT length {n} is not a power of two");
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    // e^(-2πik/n) for the first half of the circle; a stage of length `len` uses every
+    // (n/len)th one
+    let twiddles: Vec<(f64, f64)> = (0..n / 2)
+        .map(|k| {
+            let (sin, cos) = (-2.0 * PI * k as f64 / n as f64).sin_cos();
+            (cos, sin)
+        })
+        .collect();
+
+    let mut len = 2;
+    while len <= n {
+        let stride = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (cos, sin) = twiddles[k * stride];
+                let (re, im) = data[start + k + len / 2];
+                let twiddled = (re * cos - im * sin, re * sin + im * cos);
+                let even = data[start + k];
+                data[start + k] = (even.0 + twiddled.0, even.1 + twiddled.1);
+                data[start + k + len / 2] = (even.0 - twiddled.0, even.1 - twiddled.1);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scale, Spectrum, Window, spectrogram, thd};
+    use crate::signal::Sample;
+    use std::f64::consts::TAU;
+
+    /// 4096 samples at 100 kHz of `Σ amplitude · sin(2π · n · f · t)` over `(n, amplitude)`.
+    fn record(frequency: f64, components: &[(f64, f64)]) -> Vec<Sample> {
+        (0..4096)
+            .map(|i| {
+                let t = i as f64 / 100e3;
+                let v = components
+                    .iter()
+                    .map(|(n, amplitude)| amplitude * (TAU * n * frequency * t).sin())
+                    .sum();
+                Sample { t, v }
+            })
+            .collect()
+    }
+
+    fn spectrum(samples: &[Sample], window: Window) -> Spectrum {
+        let Some(spectrum) = Spectrum::of(samples, window) else {
+            panic!("no spectrum");
+        };
+        spectrum
+    }
+
+    #[test]
+    fn bin_centred_sine_reads_its_rms() {
+        // 100 kHz / 4096 bins: bin 41 is 1000.98 Hz
+        let frequency = 41.0 * 100e3 / 4096.0;
+        let samples = record(frequency, &[(1.0, 2.0)]);
+        for window in Window::ALL {
+            let spectrum = spectrum(&samples, window);
+            let peak = spectrum.peaks(1);
+            assert_eq!(peak, [41], "{window:?}");
+            assert!(
+                (spectrum.vrms[41] - 2.0_f64.sqrt()).abs() < 1e-3,
+                "{window:?}: {}",
+                spectrum.vrms[41]
+            );
+        }
+    }
+
+    #[test]
+    fn flat_top_amplitude_between_bins() {
+        // Half way between two bins, the flat top window is still accurate in dB
+        let frequency = 41.5 * 100e3 / 4096.0;
+        let spectrum = spectrum(&record(frequency, &[(1.0, 1.0)]), Window::FlatTop);
+        let peak = spectrum.peaks(1)[0];
+        let db = Scale::DbV.apply(spectrum.vrms[peak]);
+        let expected = Scale::DbV.apply(0.5_f64.sqrt());
+        assert!((db - expected).abs() < 0.05, "{db} dBV");
+    }
+
+    #[test]
+    fn flat_topped_peak() {
+        let flat = Spectrum {
+            bin_width: 1.0,
+            vrms: vec![0.0, 0.1, 1.0, 1.0, 0.1, 0.0],
+            power: vec![0.0, 0.01, 1.0, 1.0, 0.01, 0.0],
+            power_scale: 2.0,
+            window: Window::Rectangular,
+        };
+        assert_eq!(flat.peaks(2), [2], "two equal bins make one peak");
+
+        // Half way between two bins the rectangular window splits the tone evenly
+        let frequency = 41.5 * 100e3 / 4096.0;
+        let spectrum = spectrum(&record(frequency, &[(1.0, 1.0)]), Window::Rectangular);
+        assert!(
+            matches!(spectrum.peaks(1)[..], [41 | 42]),
+            "peak: {:?}",
+            spectrum.peaks(1)
+        );
+        let harmonics = spectrum.harmonics(3);
+        assert!(
+            harmonics
+                .first()
+                .is_some_and(|h| (h.frequency - frequency).abs() < 100e3 / 4096.0),
+            "fundamental found: {harmonics:?}"
+        );
+        assert!(thd(&harmonics).is_some(), "THD of a pure tone");
+    }
+
+    #[test]
+    fn harmonics_and_thd() {
+        let samples = record(1000.0, &[(1.0, 1.0), (3.0, 0.1), (5.0, 0.05)]);
+        for window in [Window::Hann, Window::BlackmanHarris, Window::FlatTop] {
+            let harmonics = spectrum(&samples, window).harmonics(5);
+            assert_eq!(harmonics.len(), 5, "{window:?}");
+            for (harmonic, (frequency, amplitude)) in harmonics.iter().zip([
+                (1000.0, 1.0),
+                (2000.0, 0.0),
+                (3000.0, 0.1),
+                (4000.0, 0.0),
+                (5000.0, 0.05),
+            ]) {
+                assert!(
+                    (harmonic.frequency - frequency).abs() < 5.0,
+                    "{window:?}: {harmonic:?}"
+                );
+                let vrms = amplitude / 2.0_f64.sqrt();
+                assert!(
+                    (harmonic.vrms - vrms).abs() < 0.01 * vrms + 1e-3,
+                    "{window:?}: {harmonic:?}, expected {vrms} V"
+                );
+            }
+            let thd = thd(&harmonics).unwrap_or_default();
+            assert!(
+                (thd - 0.0125_f64.sqrt()).abs() < 2e-3,
+                "{window:?}: THD {thd}"
+            );
+        }
+    }
+
+    #[test]
+    fn too_few_samples() {
+        assert_eq!(Spectrum::of(&[], Window::Hann), None, "empty");
+    }
+
+    #[test]
+    fn spectrogram_follows_a_chirp() {
+        // 1 kHz to 21 kHz over the 4096 samples at 100 kHz
+        let duration = 4096.0 / 100e3;
+        let chirp: Vec<Sample> = (0..4096)
+            .map(|i| {
+                let t = i as f64 / 100e3;
+                let v = (TAU * (1e3 * t + 10e3 / duration * t * t)).sin();
+                Sample { t, v }
+            })
+            .collect();
+
+        let frames = spectrogram(&chirp, 256, 0.5, Window::Hann);
+        assert_eq!(frames.len(), (4096 - 256) / 128 + 1, "frame count");
+        let peaks: Vec<f64> = frames
+            .iter()
+            .map(|frame| {
+                assert_eq!(frame.vrms.len(), 129, "bins per frame");
+                frame.peaks(1).first().copied().unwrap_or(0) as f64 * frame.bin_width
+            })
+            .collect();
+        assert!(
+            peaks.windows(2).all(|pair| pair[1] >= pair[0]),
+            "peak frequency never falls: {peaks:?}"
+        );
+        let (first, last) = (peaks[0], peaks[peaks.len() - 1]);
+        assert!((first - 1.4e3).abs() < 1e3, "first frame at {first} Hz");
+        assert!((last - 20.6e3).abs() < 1e3, "last frame at {last} Hz");
+
+        let short = spectrogram(&chirp[..100], 256, 0.5, Window::Hann);
+        assert_eq!(short.len(), 1, "a short record is one frame");
+        assert_eq!(short[0].vrms.len(), 129, "zero-padded to the frame size");
+    }
+}
@@ -0,0 +1,172 @@
+//! Edge trigger.
+//!
+//! The trigger runs over acquired samples and reports when the signal crosses the trigger
+//! level, so each acquisition can be aligned to the same point of the waveform.
+
+use crate::signal::Sample;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Slope {
+    Rising,
+    Falling,
+    Either,
+}
+
+impl Slope {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rising => "Rising",
+            Self::Falling => "Falling",
+            Self::Either => "Either",
+        }
+    }
+}
+
+/// What to do when no trigger event is found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SweepMode {
+    /// Free-run if there is no trigger event, so there is always a trace.
+    Auto,
+
+    /// Only update the display on a trigger event.
+    Normal,
+
+    /// Wait for one trigger event, then stop.
+    Single,
+}
+
+impl SweepMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "Auto",
+            Self::Normal => "Normal",
+            Self::Single => "Single",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Trigger {
+    /// Index of the channel the trigger looks at.
+    pub source: usize,
+
+    pub mode: SweepMode,
+    pub slope: Slope,
+
+    /// V
+    pub level: f64,
+
+    /// Minimum time (s) between two trigger events.
+    pub holdoff: f64,
+}
+
+impl Default for Trigger {
+    fn default() -> Self {
+        Self {
+            source: 0,
+            mode: SweepMode::Auto,
+            slope: Slope::Rising,
+            level: 0.0,
+            holdoff: 0.0,
+        }
+    }
+}
+
+impl Trigger {
+    /// Time of the first edge in `samples` that is later than `after`.
+    ///
+    /// The crossing time is linearly interpolated between the two samples around it.
+    pub fn find(&self, samples: &[Sample], after: f64) -> Option<f64> {
+        let level = self.level;
+        samples.windows(2).find_map(|pair| {
+            let (a, b) = (pair[0], pair[1]);
+            let rising = a.v < level && level <= b.v;
+            let falling = a.v > level && level >= b.v;
+            let hit = match self.slope {
+                Slope::Rising => rising,
+                Slope::Falling => falling,
+                Slope::Either => rising || falling,
+            };
+            if !hit {
+                return None;
+            }
+            let t = a.t + (level - a.v) / (b.v - a.v) * (b.t - a.t);
+            (t > after).then_some(t)
+        })
+    }
+
+    /// The next trigger event in `samples`, respecting holdoff after the `previous` event.
+    pub fn next_event(&self, samples: &[Sample], previous: Option<f64>) -> Option<f64> {
+        let after = previous.map_or(f64::NEG_INFINITY, |t| t + self.holdoff);
+        self.find(samples, after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Slope, Trigger};
+    use crate::signal::{Generator, SignalSource as _, WaveformType};
+
+    fn sine_record() -> Vec<crate::signal::Sample> {
+        let generator = Generator {
+            waveform_type: WaveformType::Sine,
+            freq: 1.0,
+            amplitude: 1.0,
+            ..Default::default()
+        };
+        generator.samples(0.0, 3.0, 3001)
+    }
+
+    #[test]
+    fn finds_rising_and_falling_edges() {
+        let samples = sine_record();
+        let mut trigger = Trigger {
+            level: 0.5,
+            ..Default::default()
+        };
+
+        // sin(2πt) = 0.5 rising at t = 1/12, falling at t = 5/12
+        let rising = trigger.find(&samples, f64::NEG_INFINITY);
+        assert!(
+            (rising.unwrap_or_default() - 1.0 / 12.0).abs() < 1e-4,
+            "{rising:?}"
+        );
+
+        trigger.slope = Slope::Falling;
+        let falling = trigger.find(&samples, f64::NEG_INFINITY);
+        assert!(
+            (falling.unwrap_or_default() - 5.0 / 12.0).abs() < 1e-4,
+            "{falling:?}"
+        );
+
+        trigger.slope = Slope::Either;
+        let either = trigger.find(&samples, 0.2);
+        assert!(
+            (either.unwrap_or_default() - 5.0 / 12.0).abs() < 1e-4,
+            "{either:?}"
+        );
+    }
+
+    #[test]
+    fn holdoff_skips_events() {
+        let samples = sine_record();
+        let trigger = Trigger {
+            holdoff: 1.5,
+            ..Default::default()
+        };
+        // Rising zero crossings are at t = 1, 2 (t = 0 is the first sample, so not a crossing)
+        let first = trigger.next_event(&samples, None);
+        assert!((first.unwrap_or_default() - 1.0).abs() < 1e-4, "{first:?}");
+        assert_eq!(trigger.next_event(&samples, first), None);
+    }
+
+    #[test]
+    fn no_event_outside_signal_range() {
+        let trigger = Trigger {
+            level: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(trigger.find(&sine_record(), f64::NEG_INFINITY), None);
+    }
+}